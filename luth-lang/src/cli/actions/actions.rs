@@ -1,17 +1,92 @@
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+
 use crate::runtime;
+use crate::runtime::interpreter::profiler::Profiler;
+
+use super::utils::{read_file, STDIN};
 
-use super::utils::read_file;
+/// Runs a file, or stdin for `-`, with `args` in the program's `args` list.
+/// `profile` prints where the time went to stderr and `profile_stacks`
+/// names a file for its collapsed call stacks. Exits with the program's
+/// status.
+pub fn run_action(file_path: &str, args: &[String], profile: bool, profile_stacks: Option<&str>) {
+    let input = match read_file(file_path) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    };
+    let script_path = (file_path != STDIN).then_some(Path::new(file_path));
+    exit(run_source(
+        &input,
+        script_path,
+        args,
+        profile,
+        profile_stacks,
+    ));
+}
 
-pub fn run_action(file_path: &str) {
-    let input = read_file(file_path);
+/// Runs `code` given on the command line, resolving imports against the
+/// current directory.
+pub fn eval_action(code: &str, args: &[String]) {
+    exit(run_source(code, None, args, false, None));
+}
 
-    let tokens = runtime::lexer::lexer::lexer(&input);
-    let mut parser = runtime::parser::parser::Parser::new(tokens);
-    match parser.parse() {
-        Ok(statements) => {
-            let mut interpreter = runtime::interpreter::interpreter::Interpreter::new();
-            interpreter.interpret(statements);
+fn run_source(
+    input: &str,
+    script_path: Option<&Path>,
+    args: &[String],
+    profile: bool,
+    profile_stacks: Option<&str>,
+) -> i32 {
+    let (tokens, lines) = runtime::lexer::lexer::lexer_with_lines(input);
+    let mut parser = runtime::parser::parser::Parser::with_lines(tokens, lines);
+    let statements = match parser.parse() {
+        Ok(statements) => statements,
+        Err(error) => {
+            eprintln!("{}", error);
+            return 1;
         }
-        Err(error) => eprintln!("{}", error),
+    };
+    for warning in parser.warnings() {
+        eprintln!("Warning: {}", warning);
+    }
+
+    let mut interpreter = runtime::interpreter::interpreter::Interpreter::new();
+    if let Some(script_path) = script_path {
+        interpreter.set_script_path(script_path);
+    }
+    interpreter.set_args(args);
+    if profile || profile_stacks.is_some() {
+        interpreter.set_profiler(Profiler::new());
+    }
+    let status = interpreter.interpret(statements);
+
+    if let Some(profiler) = interpreter.take_profiler() {
+        let base = env::current_dir()
+            .and_then(|dir| dir.canonicalize())
+            .unwrap_or_default();
+        if profile {
+            eprint!("{}", profiler.report(&base));
+        }
+        if let Some(path) = profile_stacks {
+            if let Err(error) = fs::write(path, profiler.collapsed_stacks(&base)) {
+                eprintln!("{}: {}", path, error);
+            }
+        }
+    }
+    status
+}
+
+fn exit(status: i32) {
+    if status != 0 {
+        // Nothing is flushed once the process exits.
+        let _ = io::stdout().flush();
+        process::exit(status);
     }
 }
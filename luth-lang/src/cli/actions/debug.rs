@@ -0,0 +1,12 @@
+use std::io;
+use std::process;
+
+use crate::dap::server::serve;
+
+pub fn debug_action() {
+    if let Err(error) = serve(io::BufReader::new(io::stdin()), io::stdout().lock()) {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+    process::exit(0);
+}
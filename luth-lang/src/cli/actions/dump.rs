@@ -0,0 +1,102 @@
+use std::process;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::runtime::lexer::lexer::lexer_with_lines;
+use crate::runtime::lexer::token::Token;
+use crate::runtime::parser::parser::Parser;
+
+use super::utils::read_file;
+
+#[derive(Serialize)]
+struct LineToken {
+    line: usize,
+    token: Token,
+}
+
+/// Prints the tokens of a file, one per line with the line it is on.
+pub fn tokens_action(file_path: &str, json: bool) {
+    let (tokens, lines) = lexer_with_lines(&source(file_path));
+    let tokens: Vec<LineToken> = tokens
+        .into_iter()
+        .zip(lines)
+        .map(|(token, line)| LineToken { line, token })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&tokens).unwrap());
+        return;
+    }
+    for token in &tokens {
+        // Tokens without a value are bare names, not strings.
+        let head = match serde_json::to_value(&token.token).unwrap() {
+            Value::String(name) => name,
+            value => node(&value).0,
+        };
+        println!("{:>4}  {}", token.line, head);
+    }
+}
+
+/// Prints the syntax tree of a file. Exits with a failure status if the
+/// file does not parse.
+pub fn ast_action(file_path: &str, json: bool) {
+    let (tokens, lines) = lexer_with_lines(&source(file_path));
+    let statements = match Parser::with_lines(tokens, lines).parse() {
+        Ok(statements) => statements,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    };
+
+    let value = serde_json::to_value(&statements).unwrap();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&value).unwrap());
+        return;
+    }
+    for line in node(&value).1 {
+        println!("{}", line);
+    }
+}
+
+fn source(file_path: &str) -> String {
+    read_file(file_path).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    })
+}
+
+// Renders the JSON form of a value as an indented tree: the text that goes
+// after its name, and the lines below it. Enum variants are objects with a
+// single capitalized key, shown by name with their contents nested.
+fn node(value: &Value) -> (String, Vec<String>) {
+    match value {
+        Value::Array(items) if items.is_empty() => ("[]".to_string(), Vec::new()),
+        Value::Array(items) => {
+            let mut lines = Vec::new();
+            for item in items {
+                let (head, body) = node(item);
+                lines.push(format!("- {}", head).trim_end().to_string());
+                lines.extend(body.into_iter().map(|line| format!("  {}", line)));
+            }
+            (String::new(), lines)
+        }
+        Value::Object(fields) => {
+            if let [(name, inner)] = fields.iter().collect::<Vec<_>>().as_slice() {
+                if name.starts_with(char::is_uppercase) {
+                    let (head, body) = node(inner);
+                    return (format!("{} {}", name, head).trim_end().to_string(), body);
+                }
+            }
+            let mut lines = Vec::new();
+            for (name, inner) in fields {
+                let (head, body) = node(inner);
+                lines.push(format!("{}: {}", name, head).trim_end().to_string());
+                lines.extend(body.into_iter().map(|line| format!("  {}", line)));
+            }
+            (String::new(), lines)
+        }
+        scalar => (scalar.to_string(), Vec::new()),
+    }
+}
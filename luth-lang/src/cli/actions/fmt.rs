@@ -0,0 +1,58 @@
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use crate::formatter::config::FormatConfig;
+use crate::formatter::printer::format;
+use crate::project::manifest::MANIFEST_FILE;
+
+use super::utils::source_files;
+
+/// Formats the given files in place, with the settings of ./luth.toml if
+/// there is one. With `check`, nothing is written and the process fails if
+/// any file is not already formatted.
+pub fn fmt_action(paths: &[String], check: bool) {
+    let config = if Path::new(MANIFEST_FILE).exists() {
+        FormatConfig::load(Path::new(MANIFEST_FILE))
+    } else {
+        Ok(FormatConfig::default())
+    };
+    let config = config.unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    });
+
+    let paths = if paths.is_empty() {
+        vec![".".to_string()]
+    } else {
+        paths.to_vec()
+    };
+
+    let mut failed = false;
+    for path in source_files(&paths) {
+        let result = fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|source| format(&source, &config).map(|formatted| (source, formatted)));
+
+        match result {
+            Ok((source, formatted)) if source != formatted => {
+                if check {
+                    println!("Would reformat {}", path.display());
+                    failed = true;
+                } else if let Err(error) = fs::write(&path, formatted) {
+                    eprintln!("{}: {}", path.display(), error);
+                    failed = true;
+                }
+            }
+            Ok(_) => {}
+            Err(error) => {
+                eprintln!("{}: {}", path.display(), error);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
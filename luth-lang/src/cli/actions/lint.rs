@@ -0,0 +1,85 @@
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use serde::Serialize;
+
+use crate::linter::config::{LintConfig, CONFIG_FILE};
+use crate::linter::linter::{lint, Diagnostic};
+use crate::runtime;
+
+use super::utils::source_files;
+
+#[derive(Serialize)]
+struct Report<'a> {
+    file: String,
+    #[serde(flatten)]
+    diagnostic: &'a Diagnostic,
+}
+
+/// Lints the given files, printing one line per problem or, with `json`,
+/// a JSON array. Exits with a failure status if anything was reported.
+pub fn lint_action(paths: &[String], config: Option<&str>, json: bool) {
+    let config = match config {
+        Some(path) => LintConfig::load(Path::new(path)),
+        None if Path::new(CONFIG_FILE).exists() => LintConfig::load(Path::new(CONFIG_FILE)),
+        None => Ok(LintConfig::default()),
+    };
+    let config = config.unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    });
+
+    let paths = if paths.is_empty() {
+        vec![".".to_string()]
+    } else {
+        paths.to_vec()
+    };
+
+    let mut failed = false;
+    let mut results = Vec::new();
+    for path in source_files(&paths) {
+        let parsed = fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|source| {
+                let (tokens, lines) = runtime::lexer::lexer::lexer_with_lines(&source);
+                runtime::parser::parser::Parser::with_lines(tokens, lines).parse()
+            });
+
+        match parsed {
+            Ok(statements) => results.push((path, lint(&statements, &config))),
+            Err(error) => {
+                eprintln!("{}: {}", path.display(), error);
+                failed = true;
+            }
+        }
+    }
+
+    let reports: Vec<Report> = results
+        .iter()
+        .flat_map(|(path, diagnostics)| {
+            diagnostics.iter().map(|diagnostic| Report {
+                file: path.display().to_string(),
+                diagnostic,
+            })
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports).unwrap());
+    } else {
+        for report in &reports {
+            println!(
+                "{}:{}: {}: {}",
+                report.file,
+                report.diagnostic.line,
+                report.diagnostic.rule.name(),
+                report.diagnostic.message
+            );
+        }
+    }
+
+    if failed || !reports.is_empty() {
+        process::exit(1);
+    }
+}
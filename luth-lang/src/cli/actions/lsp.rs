@@ -0,0 +1,14 @@
+use std::io;
+use std::process;
+
+use crate::lsp::server::serve;
+
+pub fn lsp_action() {
+    match serve(io::stdin().lock(), io::stdout().lock()) {
+        Ok(code) => process::exit(code),
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    }
+}
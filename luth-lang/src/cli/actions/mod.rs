@@ -1,2 +1,11 @@
+#[allow(clippy::module_inception)]
 pub mod actions;
-mod utils;
\ No newline at end of file
+pub mod debug;
+pub mod dump;
+pub mod fmt;
+pub mod lint;
+pub mod lsp;
+pub mod project;
+pub mod test;
+mod utils;
+pub mod watch;
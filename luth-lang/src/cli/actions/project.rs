@@ -0,0 +1,58 @@
+use std::env;
+use std::path::Path;
+use std::process;
+
+use crate::project::manifest::{Manifest, MANIFEST_FILE};
+use crate::project::scaffold::scaffold;
+
+/// Creates a project in a new directory at `path`, named after it.
+pub fn new_action(path: &str) {
+    let dir = Path::new(path);
+    if dir.exists() {
+        fail(format!("'{}' already exists", path));
+    }
+    let name = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    create(dir, &name);
+}
+
+/// Turns the working directory into a project, named after the directory
+/// unless `name` is given.
+pub fn init_action(name: Option<&str>) {
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => env::current_dir()
+            .ok()
+            .and_then(|dir| Some(dir.file_name()?.to_string_lossy().to_string()))
+            .unwrap_or_default(),
+    };
+    create(Path::new("."), &name);
+}
+
+/// The entry point of the project in the working directory, for `luth run`
+/// without a file. Exits with a failure status if there is none.
+pub fn manifest_entry() -> String {
+    let manifest = Manifest::find().unwrap_or_else(|error| fail(error));
+    match manifest.and_then(|manifest| manifest.entry()) {
+        Some(entry) => entry.to_string_lossy().to_string(),
+        None => fail(format!(
+            "No file to run: give one, or run from a project whose {} has a [package] table",
+            MANIFEST_FILE
+        )),
+    }
+}
+
+fn create(dir: &Path, name: &str) {
+    let written = scaffold(dir, name).unwrap_or_else(|error| fail(error));
+    println!("Created package '{}'", name);
+    for file in written {
+        println!("  {}", file.display());
+    }
+}
+
+fn fail(error: String) -> ! {
+    eprintln!("{}", error);
+    process::exit(1);
+}
@@ -0,0 +1,81 @@
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::sync::{Arc, Mutex};
+
+use crate::runtime::interpreter::coverage::Coverage;
+use crate::testing::runner::{run_tests, TEST_SUFFIX};
+
+use super::utils::source_files;
+
+/// Runs the tests in every `*_test.lt` file under `paths`, and in any file
+/// named directly. Exits with a failure status if a test fails or a file
+/// cannot be run. With `coverage`, an LCOV report is written there.
+pub fn test_action(paths: &[String], filter: Option<&str>, coverage: Option<&str>) {
+    let paths = if paths.is_empty() {
+        vec![".".to_string()]
+    } else {
+        paths.to_vec()
+    };
+
+    let files = source_files(&paths).into_iter().filter(|file| {
+        file.to_string_lossy().ends_with(TEST_SUFFIX)
+            || paths.iter().any(|path| Path::new(path) == file)
+    });
+
+    let recorded = coverage.map(|_| Arc::new(Mutex::new(Coverage::default())));
+    let (mut passed, mut failed) = (0, 0);
+    let mut broken = false;
+    for path in files {
+        let outcomes = fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|source| run_tests(&source, &path, filter, recorded.as_ref()));
+        let outcomes = match outcomes {
+            Ok(outcomes) if outcomes.is_empty() => continue,
+            Ok(outcomes) => outcomes,
+            Err(error) => {
+                eprintln!("{}: {}", path.display(), error);
+                broken = true;
+                continue;
+            }
+        };
+
+        println!("{}", path.display());
+        for outcome in outcomes {
+            let Some(failure) = outcome.failure else {
+                println!("  ok    {}", outcome.name);
+                passed += 1;
+                continue;
+            };
+
+            println!("  FAIL  {}", outcome.name);
+            for line in failure.lines() {
+                println!("        {}", line);
+            }
+            if !outcome.output.is_empty() {
+                println!("        output:");
+                for line in outcome.output.lines() {
+                    println!("          {}", line);
+                }
+            }
+            failed += 1;
+        }
+    }
+
+    println!("\n{} passed, {} failed", passed, failed);
+
+    if let (Some(output), Some(recorded)) = (coverage, recorded) {
+        let recorded = recorded.lock().unwrap();
+        let base = std::env::current_dir()
+            .and_then(|dir| dir.canonicalize())
+            .unwrap_or_default();
+        println!("\n{}", recorded.summary(&base).trim_end());
+        if let Err(error) = fs::write(output, recorded.lcov()) {
+            eprintln!("{}: {}", output, error);
+            broken = true;
+        }
+    }
+    if broken || failed > 0 {
+        process::exit(1);
+    }
+}
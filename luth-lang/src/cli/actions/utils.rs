@@ -1,5 +1,54 @@
 use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 
-pub fn read_file(file_path: &str) -> String {
-    fs::read_to_string(file_path).unwrap()
+use crate::runtime::interpreter::module::EXTENSION;
+
+/// The file name that stands for standard input.
+pub const STDIN: &str = "-";
+
+/// The contents of a source file, or of stdin for `-`.
+pub fn read_file(file_path: &str) -> Result<String, String> {
+    let mut source = String::new();
+    let result = if file_path == STDIN {
+        io::stdin().read_to_string(&mut source).map(|_| source)
+    } else {
+        fs::read_to_string(file_path)
+    };
+    result.map_err(|error| format!("Cannot read '{}': {}", file_path, error))
+}
+
+/// Every Luth source file under `paths`, searching directories
+/// recursively and skipping hidden ones.
+pub fn source_files(paths: &[String]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        collect_sources(Path::new(path), true, &mut files);
+    }
+    files
+}
+
+fn collect_sources(path: &Path, explicit: bool, files: &mut Vec<PathBuf>) {
+    if path.is_dir() {
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if hidden && !explicit {
+            return;
+        }
+        let Ok(entries) = fs::read_dir(path) else {
+            return;
+        };
+        let mut entries: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+        entries.sort();
+        for entry in entries {
+            collect_sources(&entry, false, files);
+        }
+    } else if explicit
+        || path
+            .extension()
+            .is_some_and(|extension| extension == EXTENSION)
+    {
+        files.push(path.to_path_buf());
+    }
 }
@@ -0,0 +1,136 @@
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, Command};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::runtime::interpreter::module::{imported_paths, ModuleLoader};
+use crate::runtime::lexer::lexer::lexer;
+use crate::runtime::parser::parser::Parser;
+
+use super::utils::STDIN;
+
+// How often files are checked for changes.
+const POLL: Duration = Duration::from_millis(100);
+// How long files must stay unchanged before the program runs again, so a
+// save that writes several times runs it once.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Runs a file with `luth run`, then again whenever it or a module it
+/// imports changes, until interrupted. A run that is still going when a
+/// file changes is stopped.
+pub fn watch_action(file_path: &str, args: &[String], profile: bool, profile_stacks: Option<&str>) {
+    if file_path == STDIN {
+        eprintln!("Cannot watch stdin");
+        process::exit(1);
+    }
+    let executable = env::current_exe().unwrap_or_else(|error| {
+        eprintln!("Cannot find the luth executable: {}", error);
+        process::exit(1);
+    });
+
+    let mut run_options = Vec::new();
+    if profile {
+        run_options.push("--profile");
+    }
+    if let Some(path) = profile_stacks {
+        run_options.extend(["--profile-stacks", path]);
+    }
+
+    loop {
+        // Clears the screen and moves the cursor to the top.
+        print!("\x1b[2J\x1b[H");
+        let _ = io::stdout().flush();
+
+        let files = watched_files(Path::new(file_path));
+        let mut child = Command::new(&executable)
+            .arg("run")
+            .args(&run_options)
+            .arg(file_path)
+            .args(args)
+            .spawn()
+            .map_err(|error| eprintln!("Cannot run '{}': {}", file_path, error))
+            .ok();
+
+        wait_for_change(&files, &mut child);
+        if let Some(mut child) = child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+// Returns once one of `files` changes, reporting how the run in `child`
+// ended if it does so first.
+fn wait_for_change(files: &[PathBuf], child: &mut Option<Child>) {
+    let mut running = child.is_some();
+    let stamps = modified(files);
+    loop {
+        thread::sleep(POLL);
+
+        if let (true, Some(child)) = (running, child.as_mut()) {
+            if let Ok(Some(status)) = child.try_wait() {
+                running = false;
+                match status.code() {
+                    Some(0) => eprintln!("\n[finished, waiting for changes]"),
+                    Some(code) => eprintln!("\n[exited with status {}, waiting for changes]", code),
+                    None => eprintln!("\n[stopped, waiting for changes]"),
+                }
+            }
+        }
+
+        let mut current = modified(files);
+        if current != stamps {
+            loop {
+                thread::sleep(DEBOUNCE);
+                let settled = modified(files);
+                if settled == current {
+                    return;
+                }
+                current = settled;
+            }
+        }
+    }
+}
+
+fn modified(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| fs::metadata(file).and_then(|meta| meta.modified()).ok())
+        .collect()
+}
+
+// The entry file and every module it imports, directly or not. Files that
+// cannot be read or parsed are still watched, but their imports are not
+// known until they are fixed.
+fn watched_files(entry: &Path) -> Vec<PathBuf> {
+    let loader = ModuleLoader::from_env();
+    let entry = entry.canonicalize().unwrap_or_else(|_| entry.to_path_buf());
+    let mut files = vec![entry.clone()];
+    let mut pending = VecDeque::from([entry]);
+
+    while let Some(file) = pending.pop_front() {
+        let Ok(source) = fs::read_to_string(&file) else {
+            continue;
+        };
+        let Ok(statements) = Parser::new(lexer(&source)).parse() else {
+            continue;
+        };
+        for path in imported_paths(&statements) {
+            // Imports of names bound earlier, like `from alias import x`,
+            // do not resolve and need no watching.
+            let Ok(module) = loader.resolve(&path, Some(&file)) else {
+                continue;
+            };
+            if !files.contains(&module) {
+                files.push(module.clone());
+                pending.push_back(module);
+            }
+        }
+    }
+
+    files
+}
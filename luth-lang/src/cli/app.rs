@@ -1,24 +1,176 @@
 extern crate clap;
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
 
-use super::actions::actions::run_action;
+use super::actions::actions::{eval_action, run_action};
+use super::actions::debug::debug_action;
+use super::actions::dump::{ast_action, tokens_action};
+use super::actions::fmt::fmt_action;
+use super::actions::lint::lint_action;
+use super::actions::lsp::lsp_action;
+use super::actions::project::{init_action, manifest_entry, new_action};
+use super::actions::test::test_action;
+use super::actions::watch::watch_action;
 
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Execute a file
-    Run { file_execute: String },
+    Run {
+        /// The file to run, or - to read the program from stdin (defaults to
+        /// the entry point in ./luth.toml)
+        file_execute: Option<String>,
+        /// Arguments passed to the program in its `args` list
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+        /// Print the time spent on each line and in each function
+        #[arg(long)]
+        profile: bool,
+        /// Write the profile's call stacks to FILE in collapsed format, for
+        /// flame graph tools
+        #[arg(long, value_name = "FILE")]
+        profile_stacks: Option<String>,
+        /// Run again whenever the file or a module it imports changes
+        #[arg(long)]
+        watch: bool,
+    },
+    /// Format source files in place
+    Fmt {
+        /// Files or directories to format (defaults to the current directory)
+        paths: Vec<String>,
+        /// Report unformatted files instead of rewriting them
+        #[arg(long)]
+        check: bool,
+    },
+    /// Report likely mistakes in source files
+    Lint {
+        /// Files or directories to lint (defaults to the current directory)
+        paths: Vec<String>,
+        /// Rule settings to use instead of ./luth.toml
+        #[arg(long)]
+        config: Option<String>,
+        /// Print the problems as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Start a language server on stdin and stdout
+    Lsp,
+    /// Start a debug adapter on stdin and stdout
+    Debug,
+    /// Run the tests in *_test.lt files
+    Test {
+        /// Files or directories to search (defaults to the current directory)
+        paths: Vec<String>,
+        /// Only run tests whose name contains this text
+        #[arg(long)]
+        filter: Option<String>,
+        /// Record which lines and branches the tests ran, printing a summary
+        /// and writing an LCOV report
+        #[arg(long)]
+        coverage: bool,
+        /// Where to write the LCOV report
+        #[arg(long, value_name = "FILE", default_value = "lcov.info")]
+        coverage_output: String,
+    },
+    /// Print the tokens of a file
+    Tokens {
+        file: String,
+        /// Print the tokens as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the syntax tree of a file
+    Ast {
+        file: String,
+        /// Print the tree as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Create a project in a new directory
+    New {
+        /// The directory to create, which names the package
+        path: String,
+    },
+    /// Make the current directory a project
+    Init {
+        /// The package name (defaults to the directory's name)
+        #[arg(long)]
+        name: Option<String>,
+    },
+    // `luth FILE ARGS...`, which is how a shebang line runs a script.
+    #[command(external_subcommand)]
+    Script(Vec<String>),
 }
 
 #[derive(Debug, Parser)]
 struct App {
+    /// Run CODE instead of a file, passing any further arguments in `args`
+    #[arg(short, long, value_name = "CODE")]
+    eval: Option<String>,
     #[command(subcommand)]
-    command: Commands,
+    command: Option<Commands>,
 }
 
 pub fn init_cli() {
     let cli = App::parse();
 
-    match cli.command {
-        Commands::Run { file_execute } => run_action(&file_execute),
+    let command = match (cli.eval, cli.command) {
+        (Some(code), None) => return eval_action(&code, &[]),
+        (Some(code), Some(Commands::Script(args))) => return eval_action(&code, &args),
+        (Some(_), Some(_)) => App::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "--eval cannot be used with a subcommand",
+            )
+            .exit(),
+        (None, Some(command)) => command,
+        (None, None) => {
+            let _ = App::command().print_help();
+            std::process::exit(2);
+        }
+    };
+
+    match command {
+        Commands::Run {
+            file_execute,
+            args,
+            profile,
+            profile_stacks,
+            watch: false,
+        } => {
+            let file = file_execute.unwrap_or_else(manifest_entry);
+            run_action(&file, &args, profile, profile_stacks.as_deref())
+        }
+        Commands::Run {
+            file_execute,
+            args,
+            profile,
+            profile_stacks,
+            watch: true,
+        } => {
+            let file = file_execute.unwrap_or_else(manifest_entry);
+            watch_action(&file, &args, profile, profile_stacks.as_deref())
+        }
+        Commands::Script(args) => run_action(&args[0], &args[1..], false, None),
+        Commands::Fmt { paths, check } => fmt_action(&paths, check),
+        Commands::Lint {
+            paths,
+            config,
+            json,
+        } => lint_action(&paths, config.as_deref(), json),
+        Commands::Lsp => lsp_action(),
+        Commands::Debug => debug_action(),
+        Commands::Test {
+            paths,
+            filter,
+            coverage,
+            coverage_output,
+        } => test_action(
+            &paths,
+            filter.as_deref(),
+            coverage.then_some(coverage_output.as_str()),
+        ),
+        Commands::Tokens { file, json } => tokens_action(&file, json),
+        Commands::Ast { file, json } => ast_action(&file, json),
+        Commands::New { path } => new_action(&path),
+        Commands::Init { name } => init_action(name.as_deref()),
     }
 }
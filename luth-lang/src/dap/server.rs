@@ -0,0 +1,452 @@
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::{fs, thread};
+
+use serde_json::{json, Value};
+
+use crate::lsp::server::{read_message, write_message};
+use crate::runtime::interpreter::debugger::{
+    Breakpoints, Debugger, Event, FileBreakpoints, Request, Resume, Scope, Variable,
+};
+use crate::runtime::interpreter::interpreter::Interpreter;
+use crate::runtime::lexer::lexer::lexer_with_lines;
+use crate::runtime::parser::ast::Statement;
+use crate::runtime::parser::parser::Parser;
+
+// The program runs on a single thread as far as the client is concerned.
+const THREAD_ID: u64 = 1;
+
+/// What the session reacts to: a message from the client or an event from
+/// the program being debugged.
+pub enum Input {
+    Message(Value),
+    Program(Event),
+}
+
+struct Program {
+    path: PathBuf,
+    statements: Vec<Statement>,
+    stop_on_entry: bool,
+}
+
+// The program once it runs, and how to reach its debugger.
+struct Running {
+    requests: Sender<Request>,
+    breakpoints: Breakpoints,
+    pause: Arc<AtomicBool>,
+}
+
+/// A debug adapter for one client session, speaking the Debug Adapter
+/// Protocol. Breakpoints can be set in the launched file and in the modules
+/// it imports.
+pub struct Session {
+    inbox: Sender<Input>,
+    seq: u64,
+    program: Option<Program>,
+    // Lines a breakpoint can be placed on, by canonical file path. Files
+    // are parsed the first time a breakpoint is set in them.
+    lines: HashMap<PathBuf, BTreeSet<usize>>,
+    // Breakpoints set before the program started.
+    pending: HashMap<PathBuf, FileBreakpoints>,
+    running: Option<Running>,
+    stopped: bool,
+    pub done: bool,
+}
+
+/// Serves the client on `input` and `output` until it disconnects.
+pub fn serve(mut input: impl BufRead + Send + 'static, mut output: impl Write) -> io::Result<()> {
+    let (inbox, messages) = mpsc::channel();
+    let reader = inbox.clone();
+    thread::spawn(move || {
+        while let Ok(Some(message)) = read_message(&mut input) {
+            if reader.send(Input::Message(message)).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut session = Session::new(inbox);
+    for input in messages {
+        for message in session.handle(input) {
+            write_message(&mut output, &message)?;
+        }
+        if session.done {
+            break;
+        }
+    }
+    Ok(())
+}
+
+impl Session {
+    /// A session that forwards program events to `inbox`, to be handed back
+    /// to `handle`.
+    pub fn new(inbox: Sender<Input>) -> Self {
+        Session {
+            inbox,
+            seq: 0,
+            program: None,
+            lines: HashMap::new(),
+            pending: HashMap::new(),
+            running: None,
+            stopped: false,
+            done: false,
+        }
+    }
+
+    pub fn handle(&mut self, input: Input) -> Vec<Value> {
+        match input {
+            Input::Message(message) => self.request(message),
+            Input::Program(event) => self.program_event(event),
+        }
+    }
+
+    fn request(&mut self, message: Value) -> Vec<Value> {
+        let command = message["command"].as_str().unwrap_or_default().to_string();
+        let arguments = &message["arguments"];
+        let mut events = Vec::new();
+
+        let result = match command.as_str() {
+            "initialize" => Ok(capabilities()),
+            "launch" => self.launch(arguments).inspect(|_| {
+                // Breakpoints are only checked against a parsed program, so
+                // configuration starts once there is one.
+                events.push("initialized");
+            }),
+            "setBreakpoints" => Ok(self.set_breakpoints(arguments)),
+            "setExceptionBreakpoints" => Ok(Value::Null),
+            "configurationDone" => self.start(),
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => Ok(scopes(arguments)),
+            "variables" => self.variables(arguments),
+            "evaluate" => self.evaluate(arguments),
+            "continue" => self.resume(Resume::Continue),
+            "next" => self.resume(Resume::Next),
+            "stepIn" => self.resume(Resume::StepIn),
+            "stepOut" => self.resume(Resume::StepOut),
+            "pause" => {
+                if let Some(running) = &self.running {
+                    running.pause.store(true, Ordering::SeqCst);
+                }
+                Ok(Value::Null)
+            }
+            "disconnect" | "terminate" => {
+                self.done = true;
+                Ok(Value::Null)
+            }
+            _ => Err(format!("Unknown command '{}'", command)),
+        };
+
+        let request_seq = message["seq"].clone();
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request_seq,
+            "command": command,
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(Value::Null) => {}
+            Ok(body) => response["body"] = body,
+            Err(error) => response["message"] = json!(error),
+        }
+
+        let mut messages = vec![self.sequenced(response)];
+        for event in events {
+            messages.push(self.event(event, Value::Null));
+        }
+        messages
+    }
+
+    fn program_event(&mut self, event: Event) -> Vec<Value> {
+        match event {
+            Event::Stopped { reason, line } => {
+                self.stopped = true;
+                let body = json!({
+                    "reason": reason,
+                    "description": format!("Paused on line {}", line),
+                    "threadId": THREAD_ID,
+                    "allThreadsStopped": true,
+                });
+                vec![self.event("stopped", body)]
+            }
+            Event::Output { category, text } => {
+                let body = json!({ "category": category, "output": text });
+                vec![self.event("output", body)]
+            }
+            Event::Exited { status } => {
+                self.running = None;
+                vec![
+                    self.event("exited", json!({ "exitCode": status })),
+                    self.event("terminated", Value::Null),
+                ]
+            }
+        }
+    }
+
+    fn sequenced(&mut self, mut message: Value) -> Value {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        message
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> Value {
+        let mut message = json!({ "type": "event", "event": event });
+        if !body.is_null() {
+            message["body"] = body;
+        }
+        self.sequenced(message)
+    }
+
+    fn launch(&mut self, arguments: &Value) -> Result<Value, String> {
+        let path = arguments["program"]
+            .as_str()
+            .ok_or("Missing 'program' to launch")?;
+        let (statements, lines) = parse(Path::new(path))?;
+        self.lines.insert(canonical(Path::new(path)), lines);
+
+        self.program = Some(Program {
+            path: PathBuf::from(path),
+            statements,
+            stop_on_entry: arguments["stopOnEntry"].as_bool().unwrap_or(false),
+        });
+        Ok(Value::Null)
+    }
+
+    // Replaces the breakpoints of one source file, leaving other files'
+    // breakpoints alone.
+    fn set_breakpoints(&mut self, arguments: &Value) -> Value {
+        let requested = arguments["breakpoints"].as_array().cloned();
+        let path = canonical(Path::new(
+            arguments["source"]["path"].as_str().unwrap_or_default(),
+        ));
+        let lines = self.statement_lines(&path);
+
+        let mut breakpoints = FileBreakpoints::new();
+        let mut verified = Vec::new();
+        for breakpoint in requested.unwrap_or_default() {
+            let line = breakpoint["line"].as_u64().unwrap_or_default() as usize;
+            let condition = breakpoint["condition"]
+                .as_str()
+                .filter(|condition| !condition.trim().is_empty())
+                .map(str::to_string);
+            let valid = lines.as_ref().is_ok_and(|lines| lines.contains(&line));
+            if valid {
+                breakpoints.insert(line, condition);
+            }
+            let mut result = json!({ "verified": valid, "line": line });
+            match &lines {
+                Err(error) => result["message"] = json!(error),
+                Ok(_) if !valid => result["message"] = json!("No statement on this line"),
+                Ok(_) => {}
+            }
+            verified.push(result);
+        }
+
+        match &self.running {
+            Some(running) => {
+                running
+                    .breakpoints
+                    .lock()
+                    .unwrap()
+                    .insert(path, breakpoints);
+            }
+            None => {
+                self.pending.insert(path, breakpoints);
+            }
+        }
+        json!({ "breakpoints": verified })
+    }
+
+    // The lines of `path` a breakpoint can be placed on.
+    fn statement_lines(&mut self, path: &Path) -> Result<&BTreeSet<usize>, String> {
+        if !self.lines.contains_key(path) {
+            let (_, lines) = parse(path)?;
+            self.lines.insert(path.to_path_buf(), lines);
+        }
+        Ok(&self.lines[path])
+    }
+
+    fn start(&mut self) -> Result<Value, String> {
+        let program = self.program.take().ok_or("No program was launched")?;
+
+        let (requests, requests_receiver) = mpsc::channel();
+        let (events, events_receiver) = mpsc::channel();
+        let debugger = Debugger::new(requests_receiver, events, program.stop_on_entry);
+        let breakpoints = debugger.breakpoints();
+        breakpoints
+            .lock()
+            .unwrap()
+            .extend(std::mem::take(&mut self.pending));
+        self.running = Some(Running {
+            requests,
+            breakpoints,
+            pause: debugger.pause_flag(),
+        });
+
+        let inbox = self.inbox.clone();
+        thread::spawn(move || {
+            for event in events_receiver {
+                if inbox.send(Input::Program(event)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let exited = debugger.events();
+        thread::spawn(move || {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_script_path(&program.path);
+            interpreter.set_debugger(debugger);
+            let status = interpreter.interpret(program.statements);
+            let _ = exited.send(Event::Exited { status });
+        });
+        Ok(Value::Null)
+    }
+
+    // The debugger of a program that is paused, which is the only time it
+    // can answer.
+    fn paused(&self) -> Result<&Running, String> {
+        match &self.running {
+            Some(running) if self.stopped => Ok(running),
+            _ => Err("The program is not paused".to_string()),
+        }
+    }
+
+    fn stack_trace(&self) -> Result<Value, String> {
+        let running = self.paused()?;
+        let (reply, frames) = mpsc::channel();
+        send(running, Request::StackTrace(reply))?;
+        let frames = frames.recv().map_err(|_| "The program has exited")?;
+
+        let frames: Vec<Value> = frames
+            .into_iter()
+            .enumerate()
+            .map(|(id, (name, file, line))| {
+                let mut frame = json!({ "id": id, "name": name, "line": line, "column": 1 });
+                if let Some(file) = file {
+                    let name = file.file_name().unwrap_or_default().to_string_lossy();
+                    frame["source"] = json!({ "name": name, "path": file });
+                }
+                frame
+            })
+            .collect();
+        Ok(json!({ "stackFrames": frames, "totalFrames": frames.len() }))
+    }
+
+    fn variables(&self, arguments: &Value) -> Result<Value, String> {
+        let running = self.paused()?;
+        let (frame, scope) = decode_scope(arguments["variablesReference"].as_u64().unwrap_or(0))
+            .ok_or("Unknown variables reference")?;
+
+        let (reply, variables) = mpsc::channel();
+        send(
+            running,
+            Request::Variables {
+                frame,
+                scope,
+                reply,
+            },
+        )?;
+        let variables = variables.recv().map_err(|_| "The program has exited")?;
+        let variables: Vec<Value> = variables.iter().map(variable).collect();
+        Ok(json!({ "variables": variables }))
+    }
+
+    fn evaluate(&self, arguments: &Value) -> Result<Value, String> {
+        let running = self.paused()?;
+        let expression = arguments["expression"].as_str().unwrap_or_default();
+        let frame = arguments["frameId"].as_u64().unwrap_or(0) as usize;
+
+        let (reply, result) = mpsc::channel();
+        let request = Request::Evaluate {
+            frame,
+            expression: expression.to_string(),
+            reply,
+        };
+        send(running, request)?;
+        let value = result.recv().map_err(|_| "The program has exited")??;
+        Ok(json!({
+            "result": value.value,
+            "type": value.type_name,
+            "variablesReference": 0,
+        }))
+    }
+
+    fn resume(&mut self, resume: Resume) -> Result<Value, String> {
+        let running = self.paused()?;
+        send(running, Request::Resume(resume))?;
+        self.stopped = false;
+        Ok(match resume {
+            Resume::Continue => json!({ "allThreadsContinued": true }),
+            _ => Value::Null,
+        })
+    }
+}
+
+// Parses a file, returning its statements and the lines they start on.
+fn parse(path: &Path) -> Result<(Vec<Statement>, BTreeSet<usize>), String> {
+    let source = fs::read_to_string(path)
+        .map_err(|error| format!("Cannot read '{}': {}", path.display(), error))?;
+    let (tokens, lines) = lexer_with_lines(&source);
+    let mut parser = Parser::with_lines(tokens, lines);
+    let statements = parser
+        .parse()
+        .map_err(|error| format!("{}: {}", path.display(), error))?;
+    Ok((statements, parser.statement_lines().clone()))
+}
+
+// The path the interpreter knows a file by, so breakpoints match the file
+// that is running.
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn send(running: &Running, request: Request) -> Result<(), String> {
+    running
+        .requests
+        .send(request)
+        .map_err(|_| "The program has exited".to_string())
+}
+
+fn capabilities() -> Value {
+    json!({
+        "supportsConfigurationDoneRequest": true,
+        "supportsConditionalBreakpoints": true,
+        "supportsEvaluateForHovers": true,
+        "supportsTerminateRequest": true,
+    })
+}
+
+// Each frame has two variable references, one per scope.
+fn scopes(arguments: &Value) -> Value {
+    let frame = arguments["frameId"].as_u64().unwrap_or(0);
+    json!({
+        "scopes": [
+            { "name": "Locals", "variablesReference": frame * 2 + 1, "expensive": false },
+            { "name": "Globals", "variablesReference": frame * 2 + 2, "expensive": false },
+        ]
+    })
+}
+
+fn decode_scope(reference: u64) -> Option<(usize, Scope)> {
+    let reference = reference.checked_sub(1)?;
+    let scope = if reference % 2 == 0 {
+        Scope::Locals
+    } else {
+        Scope::Globals
+    };
+    Some(((reference / 2) as usize, scope))
+}
+
+fn variable(variable: &Variable) -> Value {
+    json!({
+        "name": variable.name,
+        "value": variable.value,
+        "type": variable.type_name,
+        "variablesReference": 0,
+    })
+}
@@ -0,0 +1,270 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+use std::{env, fs, process};
+
+use serde_json::{json, Value};
+
+use crate::dap::server::{Input, Session};
+
+const PROGRAM: &str = "\
+func square(n) {
+    let result = n * n;
+    return result;
+}
+
+var total = 0;
+var i = 0;
+while i < 4 {
+    total = total + square(i);
+    i = i + 1;
+}
+print(total);
+";
+
+// A directory of its own for each test, so that concurrent test runs never
+// share files.
+fn test_dir(name: &str) -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let id = NEXT.fetch_add(1, Ordering::SeqCst);
+    let dir = env::temp_dir().join(format!("luth_dap_{}_{}_{}", process::id(), id, name));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+struct Client {
+    session: Session,
+    inbox: Receiver<Input>,
+    seq: u64,
+    dir: PathBuf,
+    // The launched program.
+    path: PathBuf,
+}
+
+impl Client {
+    fn launch(name: &str, breakpoints: Value) -> Client {
+        Client::launch_files(name, &[("main.lt", PROGRAM)], &[("main.lt", breakpoints)])
+    }
+
+    // Writes `files` and launches the first, with breakpoints set by file
+    // name before the program starts.
+    fn launch_files(name: &str, files: &[(&str, &str)], breakpoints: &[(&str, Value)]) -> Client {
+        let dir = test_dir(name);
+        for (file, source) in files {
+            fs::write(dir.join(file), source).unwrap();
+        }
+
+        let (sender, inbox) = mpsc::channel();
+        let mut client = Client {
+            session: Session::new(sender),
+            inbox,
+            seq: 0,
+            path: dir.join(files[0].0),
+            dir,
+        };
+        client.request("initialize", json!({}));
+        let path = client.path.clone();
+        client.request("launch", json!({ "program": path }));
+        for (file, breakpoints) in breakpoints {
+            let path = client.dir.join(file);
+            client.set_breakpoints(&path, breakpoints.clone());
+        }
+        client.request("configurationDone", json!({}));
+        client
+    }
+
+    fn set_breakpoints(&mut self, path: &Path, breakpoints: Value) -> Value {
+        self.request(
+            "setBreakpoints",
+            json!({ "source": { "path": path }, "breakpoints": breakpoints }),
+        )
+    }
+
+    fn request(&mut self, command: &str, arguments: Value) -> Value {
+        self.seq += 1;
+        let message = json!({
+            "seq": self.seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        });
+        self.session.handle(Input::Message(message)).remove(0)
+    }
+
+    // The next event from the program, skipping output.
+    fn event(&mut self) -> Value {
+        loop {
+            let input = self.inbox.recv_timeout(Duration::from_secs(5)).unwrap();
+            let events = self.session.handle(input);
+            if events[0]["event"] != "output" {
+                return events[0].clone();
+            }
+        }
+    }
+
+    fn top_frame(&mut self) -> Value {
+        let trace = self.request("stackTrace", json!({ "threadId": 1 }));
+        trace["body"]["stackFrames"][0].clone()
+    }
+
+    fn evaluate(&mut self, expression: &str) -> Value {
+        let response = self.request(
+            "evaluate",
+            json!({ "expression": expression, "frameId": 0 }),
+        );
+        response["body"]["result"].clone()
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[test]
+fn breakpoints_are_verified_against_statement_lines() {
+    let mut client = Client::launch("verify", json!([]));
+    let path = client.path.clone();
+    let response = client.set_breakpoints(&path, json!([{ "line": 2 }, { "line": 5 }]));
+    let verified: Vec<_> = response["body"]["breakpoints"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|breakpoint| breakpoint["verified"].as_bool().unwrap())
+        .collect();
+    assert_eq!(verified, vec![true, false]);
+    client.request("disconnect", json!({}));
+}
+
+#[test]
+fn stops_at_breakpoint_and_inspects_frames() {
+    let mut client = Client::launch("frames", json!([{ "line": 3 }]));
+    let stopped = client.event();
+    assert_eq!(stopped["event"], "stopped");
+    assert_eq!(stopped["body"]["reason"], "breakpoint");
+
+    let trace = client.request("stackTrace", json!({ "threadId": 1 }));
+    let frames = trace["body"]["stackFrames"].as_array().unwrap();
+    let names: Vec<_> = frames.iter().map(|frame| frame["name"].clone()).collect();
+    assert_eq!(names, vec![json!("square"), json!("main")]);
+    assert_eq!(frames[0]["line"], 3);
+    assert_eq!(frames[1]["line"], 9);
+
+    let scopes = client.request("scopes", json!({ "frameId": 0 }));
+    let locals = scopes["body"]["scopes"][0]["variablesReference"].clone();
+    let variables = client.request("variables", json!({ "variablesReference": locals }));
+    assert_eq!(
+        variables["body"]["variables"],
+        json!([
+            { "name": "n", "value": "0", "type": "Int", "variablesReference": 0 },
+            { "name": "result", "value": "0", "type": "Int", "variablesReference": 0 },
+        ])
+    );
+    assert_eq!(client.evaluate("n + 10"), "10");
+    assert_eq!(client.evaluate("total"), "0");
+
+    let path = client.path.clone();
+    client.set_breakpoints(&path, json!([]));
+    client.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.event()["event"], "exited");
+}
+
+#[test]
+fn conditional_breakpoint_stops_only_when_true() {
+    let mut client = Client::launch("condition", json!([{ "line": 10, "condition": "i == 2" }]));
+    assert_eq!(client.event()["event"], "stopped");
+    assert_eq!(client.evaluate("total"), "5");
+    assert_eq!(client.evaluate("undefined_name"), Value::Null);
+
+    client.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.event()["event"], "exited");
+}
+
+#[test]
+fn stepping_in_over_and_out() {
+    let mut client = Client::launch("step", json!([{ "line": 9 }]));
+    client.event();
+
+    client.request("stepIn", json!({ "threadId": 1 }));
+    assert_eq!(client.event()["body"]["reason"], "step");
+    assert_eq!(client.top_frame()["name"], "square");
+    assert_eq!(client.top_frame()["line"], 2);
+
+    client.request("stepOut", json!({ "threadId": 1 }));
+    client.event();
+    assert_eq!(client.top_frame()["name"], "main");
+    assert_eq!(client.top_frame()["line"], 10);
+
+    client.request("next", json!({ "threadId": 1 }));
+    client.event();
+    assert_eq!(client.top_frame()["line"], 9);
+    client.request("next", json!({ "threadId": 1 }));
+    client.event();
+    assert_eq!(client.top_frame()["name"], "main");
+    assert_eq!(client.top_frame()["line"], 10);
+
+    let path = client.path.clone();
+    client.set_breakpoints(&path, json!([]));
+    client.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.event()["event"], "exited");
+}
+
+#[test]
+fn breakpoints_are_kept_per_file() {
+    let main = "\
+import \"shapes\";
+var a = shapes.area(2, 3);
+var b = shapes.area(4, 5);
+print(a + b);
+";
+    let shapes = "\
+export func area(w, h) {
+    let result = w * h;
+    return result;
+}
+";
+    let mut client = Client::launch_files(
+        "files",
+        &[("main.lt", main), ("shapes.lt", shapes)],
+        &[
+            ("main.lt", json!([{ "line": 3 }])),
+            ("shapes.lt", json!([{ "line": 2 }])),
+        ],
+    );
+
+    // Setting the module's breakpoints kept the main file's, and line 2 of
+    // the main file has none.
+    client.event();
+    let frame = client.top_frame();
+    assert_eq!(frame["name"], "area");
+    assert_eq!(frame["source"]["name"], "shapes.lt");
+    assert_eq!(client.evaluate("w"), "2");
+    let trace = client.request("stackTrace", json!({ "threadId": 1 }));
+    assert_eq!(
+        trace["body"]["stackFrames"][1]["source"]["path"],
+        json!(client.path.canonicalize().unwrap())
+    );
+
+    client.request("continue", json!({ "threadId": 1 }));
+    client.event();
+    assert_eq!(client.top_frame()["name"], "main");
+    assert_eq!(client.top_frame()["line"], 3);
+
+    // Clearing the module's breakpoints lets the second call run through.
+    let shapes = client.dir.join("shapes.lt");
+    client.set_breakpoints(&shapes, json!([]));
+    client.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.event()["event"], "exited");
+}
+
+#[test]
+fn requests_need_a_paused_program() {
+    let (sender, _inbox) = mpsc::channel();
+    let mut session = Session::new(sender);
+    let message = json!({ "seq": 1, "command": "evaluate", "arguments": { "expression": "1" } });
+    let response = session.handle(Input::Message(message)).remove(0);
+    assert_eq!(response["success"], false);
+    assert_eq!(response["message"], "The program is not paused");
+}
@@ -0,0 +1,42 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// How `luth fmt` lays out code, read from the `[format]` table of the
+/// project manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FormatConfig {
+    // Spaces per indentation level.
+    pub indent: usize,
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        FormatConfig { indent: 4 }
+    }
+}
+
+#[derive(Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    format: FormatConfig,
+}
+
+impl FormatConfig {
+    pub fn load(path: &Path) -> Result<FormatConfig, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|error| format!("Could not read '{}': {}", path.display(), error))?;
+        FormatConfig::parse(&source).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    pub fn parse(source: &str) -> Result<FormatConfig, String> {
+        let file: ConfigFile =
+            toml::from_str(source).map_err(|error| error.message().to_string())?;
+        if !(1..=16).contains(&file.format.indent) {
+            return Err("Format indent must be between 1 and 16".to_string());
+        }
+        Ok(file.format)
+    }
+}
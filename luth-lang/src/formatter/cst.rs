@@ -0,0 +1,159 @@
+use crate::runtime::lexer::lexer::lossless;
+use crate::runtime::lexer::token::Token;
+
+/// A token together with the whitespace in front of it. `token` is `None`
+/// for text the lexer does not recognize.
+#[derive(Debug, Clone)]
+pub struct Leaf {
+    pub token: Option<Token>,
+    pub text: String,
+    pub leading: String,
+}
+
+impl Leaf {
+    pub fn is(&self, token: &Token) -> bool {
+        self.token.as_ref() == Some(token)
+    }
+
+    pub fn is_comment(&self) -> bool {
+        self.is(&Token::Comment)
+    }
+
+    pub fn newlines_before(&self) -> usize {
+        self.leading.matches('\n').count()
+    }
+}
+
+/// Tokens nested by their brackets. Comments and whitespace are kept, so
+/// the tree can always be turned back into the exact source it came from.
+#[derive(Debug, Clone)]
+pub enum Node {
+    Leaf(Leaf),
+    Group {
+        open: Leaf,
+        children: Vec<Node>,
+        close: Leaf,
+    },
+}
+
+impl Node {
+    pub fn first_leaf(&self) -> &Leaf {
+        match self {
+            Node::Leaf(leaf) => leaf,
+            Node::Group { open, .. } => open,
+        }
+    }
+
+    pub fn last_leaf(&self) -> &Leaf {
+        match self {
+            Node::Leaf(leaf) => leaf,
+            Node::Group { close, .. } => close,
+        }
+    }
+
+    fn write_source(&self, out: &mut String) {
+        match self {
+            Node::Leaf(leaf) => {
+                out.push_str(&leaf.leading);
+                out.push_str(&leaf.text);
+            }
+            Node::Group {
+                open,
+                children,
+                close,
+            } => {
+                Node::Leaf(open.clone()).write_source(out);
+                for child in children {
+                    child.write_source(out);
+                }
+                Node::Leaf(close.clone()).write_source(out);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Cst {
+    pub children: Vec<Node>,
+    pub trailing: String,
+}
+
+impl Cst {
+    pub fn parse(source: &str) -> Result<Cst, String> {
+        // Each open bracket with the nodes read since it, innermost last.
+        let mut stack: Vec<(Leaf, Vec<Node>)> = Vec::new();
+        let mut children = Vec::new();
+        let mut end = 0;
+
+        for (token, span) in lossless(source) {
+            if token == Some(Token::EOF) {
+                continue;
+            }
+            let leaf = Leaf {
+                token,
+                text: source[span.clone()].to_string(),
+                leading: source[end..span.start].to_string(),
+            };
+            end = span.end;
+
+            match &leaf.token {
+                Some(Token::LeftParen | Token::LeftBracket | Token::LeftBrace) => {
+                    stack.push((leaf, std::mem::take(&mut children)));
+                }
+                Some(close @ (Token::RightParen | Token::RightBracket | Token::RightBrace)) => {
+                    let (open, outer) = stack.pop().ok_or_else(|| {
+                        format!(
+                            "Unmatched '{}' on line {}",
+                            leaf.text,
+                            line(source, span.start)
+                        )
+                    })?;
+                    if open.token != Some(opening(close)) {
+                        return Err(format!(
+                            "Mismatched '{}' on line {}",
+                            leaf.text,
+                            line(source, span.start)
+                        ));
+                    }
+                    let group = Node::Group {
+                        open,
+                        children: std::mem::replace(&mut children, outer),
+                        close: leaf,
+                    };
+                    children.push(group);
+                }
+                _ => children.push(Node::Leaf(leaf)),
+            }
+        }
+
+        if let Some((open, _)) = stack.last() {
+            return Err(format!("Unclosed '{}'", open.text));
+        }
+
+        Ok(Cst {
+            children,
+            trailing: source[end..].to_string(),
+        })
+    }
+
+    pub fn source(&self) -> String {
+        let mut out = String::new();
+        for child in &self.children {
+            child.write_source(&mut out);
+        }
+        out.push_str(&self.trailing);
+        out
+    }
+}
+
+fn opening(close: &Token) -> Token {
+    match close {
+        Token::RightParen => Token::LeftParen,
+        Token::RightBracket => Token::LeftBracket,
+        _ => Token::LeftBrace,
+    }
+}
+
+fn line(source: &str, offset: usize) -> usize {
+    source[..offset].matches('\n').count() + 1
+}
@@ -0,0 +1,5 @@
+pub mod config;
+pub mod cst;
+pub mod printer;
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,326 @@
+use crate::formatter::config::FormatConfig;
+use crate::formatter::cst::{Cst, Leaf, Node};
+use crate::runtime::lexer::lexer::lossless;
+use crate::runtime::lexer::token::Token;
+
+/// Formats `source` in the canonical style, indented as `config` says.
+/// Only whitespace changes: the tokens and comments of the result are the
+/// same as those of the input.
+pub fn format(source: &str, config: &FormatConfig) -> Result<String, String> {
+    let cst = Cst::parse(source)?;
+    debug_assert_eq!(cst.source(), source);
+    let mut printer = Printer {
+        indentation: " ".repeat(config.indent),
+        ..Printer::default()
+    };
+    printer.items(&cst.children, Layout::Block);
+    let formatted = printer.finish();
+
+    if significant_text(source) != significant_text(&formatted) {
+        return Err("Formatting would change the meaning of the program".to_string());
+    }
+    Ok(formatted)
+}
+
+fn significant_text(source: &str) -> Vec<&str> {
+    lossless(source)
+        .into_iter()
+        .map(|(_, span)| source[span].trim_end())
+        .collect()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Layout {
+    // Everything on one line: `(a, b)`, `{ x: 1 }`.
+    Inline,
+    // One statement or entry per line, as in blocks and multi-line
+    // struct declarations.
+    Block,
+    // One comma-separated element per line, for long calls and lists.
+    Multiline,
+}
+
+fn layout(open: &Leaf, children: &[Node]) -> Layout {
+    let Some(first) = children.first() else {
+        return Layout::Inline;
+    };
+    let has_comment = children.iter().any(|child| child.first_leaf().is_comment());
+    let broken = first.first_leaf().leading.contains('\n');
+
+    if open.is(&Token::LeftBrace) {
+        let has_statements = children.iter().any(|child| {
+            let leaf = child.first_leaf();
+            leaf.is(&Token::Semicolon)
+                || leaf.token.as_ref().is_some_and(starts_statement)
+                || (leaf.is(&Token::LeftBrace) && group_layout(child) == Some(Layout::Block))
+        });
+        if has_comment || has_statements || broken {
+            return Layout::Block;
+        }
+    } else if has_comment || broken {
+        return Layout::Multiline;
+    }
+    Layout::Inline
+}
+
+fn group_layout(node: &Node) -> Option<Layout> {
+    match node {
+        Node::Group { open, children, .. } => Some(layout(open, children)),
+        Node::Leaf(_) => None,
+    }
+}
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+    indent: usize,
+    // The text of one indentation level.
+    indentation: String,
+    previous: Option<Leaf>,
+    // Whether the last token was a prefix minus.
+    negation: bool,
+    // Whether we are between the pipes of a lambda's parameter list.
+    lambda: bool,
+}
+
+impl Printer {
+    fn finish(mut self) -> String {
+        self.newline();
+        if self.out.trim().is_empty() {
+            return String::new();
+        }
+        self.out
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.out.is_empty() || self.out.ends_with('\n')
+    }
+
+    fn newline(&mut self) {
+        if !self.at_line_start() {
+            self.out.push('\n');
+        }
+    }
+
+    fn blank_line(&mut self) {
+        self.newline();
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn items(&mut self, nodes: &[Node], layout: Layout) {
+        for (i, node) in nodes.iter().enumerate() {
+            let leaf = node.first_leaf();
+            let trailing_comment = leaf.is_comment() && !leaf.leading.contains('\n');
+
+            if i == 0 {
+                if !trailing_comment {
+                    self.newline();
+                }
+            } else if self.breaks_between(&nodes[i - 1], node, layout) {
+                if leaf.newlines_before() > 1 {
+                    self.blank_line();
+                } else {
+                    self.newline();
+                }
+            }
+            self.node(node);
+        }
+    }
+
+    fn breaks_between(&self, previous: &Node, next: &Node, layout: Layout) -> bool {
+        let last = previous.last_leaf();
+        let first = next.first_leaf();
+
+        if last.is_comment() {
+            return true;
+        }
+        if first.is_comment() {
+            return first.leading.contains('\n');
+        }
+        if layout == Layout::Multiline {
+            return last.is(&Token::Comma);
+        }
+        if last.is(&Token::Semicolon) {
+            return true;
+        }
+        if last.is(&Token::Comma) {
+            return first.leading.contains('\n');
+        }
+        if group_layout(previous) == Some(Layout::Block) && last.is(&Token::RightBrace) {
+            return !matches!(
+                first.token,
+                Some(
+                    Token::Else
+                        | Token::Elif
+                        | Token::Catch
+                        | Token::Finally
+                        | Token::Comma
+                        | Token::Semicolon
+                        | Token::RightParen
+                        | Token::Dot
+                        | Token::Question
+                )
+            );
+        }
+
+        first.leading.contains('\n')
+            && last.token.as_ref().is_some_and(ends_operand)
+            && first.token.as_ref().is_some_and(starts_item)
+    }
+
+    fn node(&mut self, node: &Node) {
+        match node {
+            Node::Leaf(leaf) => self.token(leaf),
+            Node::Group {
+                open,
+                children,
+                close,
+            } => {
+                self.token(open);
+                match layout(open, children) {
+                    Layout::Inline => children.iter().for_each(|child| self.node(child)),
+                    layout => {
+                        self.indent += 1;
+                        self.items(children, layout);
+                        self.indent -= 1;
+                        self.newline();
+                    }
+                }
+                self.token(close);
+            }
+        }
+    }
+
+    fn token(&mut self, leaf: &Leaf) {
+        if self.at_line_start() {
+            self.out.push_str(&self.indentation.repeat(self.indent));
+        } else if self.space_before(leaf) {
+            self.out.push(' ');
+        }
+
+        if leaf.is_comment() {
+            self.out.push_str(leaf.text.trim_end());
+        } else {
+            self.out.push_str(&leaf.text);
+        }
+
+        self.negation = leaf.is(&Token::Minus)
+            && !self
+                .previous
+                .as_ref()
+                .and_then(|previous| previous.token.as_ref())
+                .is_some_and(ends_operand);
+        if leaf.is(&Token::Pipe) {
+            self.lambda = !self.lambda;
+        }
+        self.previous = Some(leaf.clone());
+    }
+
+    fn space_before(&self, next: &Leaf) -> bool {
+        let Some(previous) = &self.previous else {
+            return false;
+        };
+        let (Some(before), Some(token)) = (&previous.token, &next.token) else {
+            return true;
+        };
+
+        match (before, token) {
+            (_, Token::Comment) => true,
+            (Token::LeftBrace, Token::RightBrace) => false,
+            (Token::LeftBrace, _) | (_, Token::RightBrace) => true,
+            (Token::LeftParen | Token::LeftBracket | Token::Dot, _) => false,
+            (
+                _,
+                Token::RightParen
+                | Token::RightBracket
+                | Token::Comma
+                | Token::Semicolon
+                | Token::Dot
+                | Token::Colon
+                | Token::Question,
+            ) => false,
+            (_, Token::Increment | Token::Decrement) => !ends_operand(before),
+            (Token::DotDot | Token::DotDotEqual, _) => false,
+            (Token::Comma, Token::DotDot | Token::DotDotEqual) => true,
+            (_, Token::DotDot | Token::DotDotEqual) => false,
+            (Token::Not, _) => previous.text != "!",
+            (Token::Minus, _) => !self.negation,
+            // Inside `|a, b|` the pipes hug the parameters.
+            (Token::Pipe, _) if self.lambda => false,
+            (_, Token::Pipe) => !self.lambda,
+            (_, Token::LeftParen) => {
+                !(ends_operand(before) || matches!(before, Token::Print | Token::Func))
+            }
+            (_, Token::LeftBracket) => !ends_operand(before),
+            _ => true,
+        }
+    }
+}
+
+// Tokens that can end an operand, after which `-` is a binary minus and
+// `(` or `[` make a call or an index.
+fn ends_operand(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Identifier(_)
+            | Token::StringLiteral(_)
+            | Token::NumberLiteral(_)
+            | Token::BooleanLiteral(_)
+            | Token::Nil
+            | Token::This
+            | Token::Super
+            | Token::Underscore
+            | Token::StringType
+            | Token::IntType
+            | Token::BooleanType
+            | Token::RightParen
+            | Token::RightBracket
+            | Token::RightBrace
+            | Token::Increment
+            | Token::Decrement
+            | Token::Question
+    )
+}
+
+fn starts_statement(token: &Token) -> bool {
+    starts_item(token) && !ends_operand(token)
+}
+
+// Tokens that can only begin a new statement or entry when they follow a
+// complete operand, such as a match arm after the previous arm's value.
+fn starts_item(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Identifier(_)
+            | Token::StringLiteral(_)
+            | Token::NumberLiteral(_)
+            | Token::BooleanLiteral(_)
+            | Token::Nil
+            | Token::This
+            | Token::Super
+            | Token::Underscore
+            | Token::Var
+            | Token::Let
+            | Token::Const
+            | Token::Print
+            | Token::If
+            | Token::While
+            | Token::Func
+            | Token::Return
+            | Token::Struct
+            | Token::Impl
+            | Token::Class
+            | Token::Enum
+            | Token::Match
+            | Token::Import
+            | Token::From
+            | Token::Export
+            | Token::Throw
+            | Token::Try
+            | Token::Thread
+            | Token::Select
+            | Token::Test
+    )
+}
@@ -0,0 +1,84 @@
+use crate::formatter::config::FormatConfig;
+use crate::formatter::cst::Cst;
+use crate::formatter::printer::format;
+use crate::runtime::lexer::lexer::lexer;
+use crate::runtime::parser::parser::Parser;
+
+fn formatted(source: &str) -> String {
+    let output = format(source, &FormatConfig::default()).unwrap();
+    assert_eq!(
+        format(&output, &FormatConfig::default()).unwrap(),
+        output,
+        "not idempotent"
+    );
+    output
+}
+
+#[test]
+fn cst_is_lossless() {
+    let source = "var x=1; # note\n\n\n#* block *#\nfunc f( a ){\n  return a  ;}\n";
+    assert_eq!(Cst::parse(source).unwrap().source(), source);
+}
+
+#[test]
+fn normalizes_spacing() {
+    assert_eq!(formatted("var x=1+2*-3;"), "var x = 1 + 2 * -3;\n");
+    assert_eq!(formatted("print ( f( a,b )[0] );"), "print(f(a, b)[0]);\n");
+    assert_eq!(formatted("var r = 1 .. 10;"), "var r = 1..10;\n");
+    assert_eq!(formatted("var s = p . x;"), "var s = p.x;\n");
+}
+
+#[test]
+fn indents_blocks() {
+    let source = "func f(a) { if a > 1 { return a; } else { return 0; } }";
+    assert_eq!(
+        formatted(source),
+        "func f(a) {\n    if a > 1 {\n        return a;\n    } else {\n        return 0;\n    }\n}\n"
+    );
+}
+
+#[test]
+fn keeps_comments_and_one_blank_line() {
+    let source = "var a = 1; # one\n\n\n\n# two\nvar b = 2;";
+    assert_eq!(formatted(source), "var a = 1; # one\n\n# two\nvar b = 2;\n");
+}
+
+#[test]
+fn keeps_inline_literals() {
+    assert_eq!(
+        formatted("var p = Point {x: 1, y: 2};"),
+        "var p = Point { x: 1, y: 2 };\n"
+    );
+    assert_eq!(
+        formatted("var l = |a, b| a + b;"),
+        "var l = |a, b| a + b;\n"
+    );
+}
+
+#[test]
+fn splits_match_arms() {
+    let source = "var s = match x {\n1 => \"one\",\n      _ => \"other\",\n};";
+    let output = formatted(source);
+    assert_eq!(
+        output,
+        "var s = match x {\n    1 => \"one\",\n    _ => \"other\",\n};\n"
+    );
+    assert!(Parser::new(lexer(&output)).parse().is_ok());
+}
+
+#[test]
+fn rejects_unbalanced_brackets() {
+    assert!(format("func f() {", &FormatConfig::default()).is_err());
+    assert!(format("print(1]);", &FormatConfig::default()).is_err());
+}
+
+#[test]
+fn indent_width_comes_from_the_config() {
+    let config = FormatConfig::parse("[format]\nindent = 2\n").unwrap();
+    assert_eq!(
+        format("if a { print(a); }", &config).unwrap(),
+        "if a {\n  print(a);\n}\n"
+    );
+    assert!(FormatConfig::parse("[format]\nindent = 0\n").is_err());
+    assert!(FormatConfig::parse("[format]\ntabs = true\n").is_err());
+}
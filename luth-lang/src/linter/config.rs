@@ -0,0 +1,86 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::project::manifest::MANIFEST_FILE;
+
+/// The file `luth lint` reads its settings from, in the `[lint]` table.
+pub const CONFIG_FILE: &str = MANIFEST_FILE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    UnusedVariable,
+    Shadowing,
+    UnreachableCode,
+    ConstantCondition,
+    MismatchedComparison,
+    SelfAssignment,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 6] = [
+        Rule::UnusedVariable,
+        Rule::Shadowing,
+        Rule::UnreachableCode,
+        Rule::ConstantCondition,
+        Rule::MismatchedComparison,
+        Rule::SelfAssignment,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::UnusedVariable => "unused-variable",
+            Rule::Shadowing => "shadowing",
+            Rule::UnreachableCode => "unreachable-code",
+            Rule::ConstantCondition => "constant-condition",
+            Rule::MismatchedComparison => "mismatched-comparison",
+            Rule::SelfAssignment => "self-assignment",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+}
+
+/// Which rules are enabled. Every rule is on unless the config turns it
+/// off with `rule-name = false`.
+#[derive(Debug, Default)]
+pub struct LintConfig {
+    disabled: HashSet<Rule>,
+}
+
+#[derive(Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    lint: HashMap<String, bool>,
+}
+
+impl LintConfig {
+    pub fn load(path: &Path) -> Result<LintConfig, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|error| format!("Could not read '{}': {}", path.display(), error))?;
+        LintConfig::parse(&source).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    pub fn parse(source: &str) -> Result<LintConfig, String> {
+        let file: ConfigFile =
+            toml::from_str(source).map_err(|error| error.message().to_string())?;
+
+        let mut config = LintConfig::default();
+        for (name, enabled) in file.lint {
+            let rule = Rule::from_name(&name).ok_or(format!("Unknown lint rule '{}'", name))?;
+            if !enabled {
+                config.disabled.insert(rule);
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn is_enabled(&self, rule: Rule) -> bool {
+        !self.disabled.contains(&rule)
+    }
+}
@@ -0,0 +1,537 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::linter::config::{LintConfig, Rule};
+use crate::runtime::interpreter::module::{exported_names, module_name};
+use crate::runtime::parser::ast::{
+    Expression, FunctionDeclaration, Operator, SelectArm, Statement,
+};
+use crate::runtime::parser::parser::pattern_bindings;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub rule: Rule,
+    // The line the problem is on, or 0 when the program was parsed without
+    // line numbers.
+    pub line: usize,
+    pub message: String,
+}
+
+/// Runs every enabled rule over a parsed program.
+pub fn lint(statements: &[Statement], config: &LintConfig) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        config,
+        diagnostics: Vec::new(),
+        line: 0,
+        scopes: vec![Vec::new()],
+        unresolved: HashSet::new(),
+        exported: exported_names(statements),
+    };
+    linter.statements(statements);
+    linter.pop_scope();
+    linter.diagnostics
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Variable,
+    Parameter,
+    // Functions, types, imports and pattern bindings: they can shadow and
+    // be shadowed, but are never reported as unused.
+    Other,
+}
+
+struct Local {
+    name: String,
+    kind: Kind,
+    line: usize,
+    used: bool,
+}
+
+struct Linter<'a> {
+    config: &'a LintConfig,
+    diagnostics: Vec<Diagnostic>,
+    // The line of the statement being checked.
+    line: usize,
+    scopes: Vec<Vec<Local>>,
+    // Names used before any declaration was in scope, as a function body
+    // may read a global declared after the function.
+    unresolved: HashSet<String>,
+    exported: HashSet<String>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, rule: Rule, message: String) {
+        self.report_at(rule, self.line, message);
+    }
+
+    fn report_at(&mut self, rule: Rule, line: usize, message: String) {
+        if self.config.is_enabled(rule) {
+            self.diagnostics.push(Diagnostic {
+                rule,
+                line,
+                message,
+            });
+        }
+    }
+
+    fn declare(&mut self, name: &str, kind: Kind) {
+        let shadows = self
+            .scopes
+            .iter()
+            .any(|scope| scope.iter().any(|local| local.name == name));
+        if shadows && kind != Kind::Other {
+            self.report(
+                Rule::Shadowing,
+                format!("'{}' shadows an earlier declaration", name),
+            );
+        }
+
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(Local {
+                name: name.to_string(),
+                kind,
+                line: self.line,
+                used: false,
+            });
+        }
+    }
+
+    fn use_name(&mut self, name: &str) {
+        let local = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.iter_mut().rev().find(|local| local.name == name));
+        match local {
+            Some(local) => local.used = true,
+            None => {
+                self.unresolved.insert(name.to_string());
+            }
+        }
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    fn pop_scope(&mut self) {
+        let top_level = self.scopes.len() == 1;
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+
+        for local in scope {
+            let used = local.used
+                || self.unresolved.contains(&local.name)
+                || (top_level && self.exported.contains(&local.name));
+            if used {
+                continue;
+            }
+            match local.kind {
+                Kind::Variable => self.report_at(
+                    Rule::UnusedVariable,
+                    local.line,
+                    format!("Variable '{}' is never used", local.name),
+                ),
+                Kind::Parameter => self.report_at(
+                    Rule::UnusedVariable,
+                    local.line,
+                    format!("Parameter '{}' is never used", local.name),
+                ),
+                Kind::Other => {}
+            }
+        }
+    }
+
+    fn scoped_statements(&mut self, statements: &[Statement]) {
+        self.push_scope();
+        self.statements(statements);
+        self.pop_scope();
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        let mut exit = None;
+        for statement in statements {
+            if let Some(keyword) = exit.take() {
+                if let Statement::Located { line, .. } = statement {
+                    self.line = *line;
+                }
+                self.report(
+                    Rule::UnreachableCode,
+                    format!("Unreachable code after '{}'", keyword),
+                );
+            }
+            self.statement(statement);
+            exit = exit.or(exits(statement));
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                self.expression(value);
+                self.declare(name, Kind::Variable);
+            }
+            Statement::VariableAssignment { name, value } => {
+                if matches!(value, Expression::Identifier(source) if source == name) {
+                    self.report(
+                        Rule::SelfAssignment,
+                        format!("'{}' is assigned to itself", name),
+                    );
+                }
+                self.expression(value);
+            }
+            Statement::PropertyAssignment {
+                object,
+                name,
+                value,
+            } => {
+                if let Expression::Get {
+                    object: source,
+                    name: property,
+                } = value
+                {
+                    if property == name && same_place(object, source) {
+                        self.report(
+                            Rule::SelfAssignment,
+                            format!("Property '{}' is assigned to itself", name),
+                        );
+                    }
+                }
+                self.expression(object);
+                self.expression(value);
+            }
+            Statement::Expression(expression)
+            | Statement::Print(expression)
+            | Statement::Throw(expression)
+            | Statement::Return(Some(expression)) => self.expression(expression),
+            Statement::Return(None) => {}
+            Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.check_condition(condition);
+                self.expression(condition);
+                self.statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            Statement::While {
+                condition, body, ..
+            } => {
+                match constant(condition) {
+                    Some(true) if !can_exit(body) => self.report(
+                        Rule::ConstantCondition,
+                        "'while true' loop never exits".to_string(),
+                    ),
+                    Some(false) => self.report(
+                        Rule::ConstantCondition,
+                        "Loop condition is always false".to_string(),
+                    ),
+                    _ => {}
+                }
+                self.expression(condition);
+                self.statement(body);
+            }
+            Statement::Block(statements)
+            | Statement::Test {
+                body: statements, ..
+            } => self.scoped_statements(statements),
+            Statement::Function(declaration) => {
+                if let Some(name) = &declaration.name {
+                    self.declare(name, Kind::Other);
+                }
+                self.function(declaration);
+            }
+            Statement::Struct { name, .. } | Statement::Enum { name, .. } => {
+                self.declare(name, Kind::Other);
+            }
+            Statement::Class { name, methods, .. } => {
+                self.declare(name, Kind::Other);
+                methods.iter().for_each(|method| self.function(method));
+            }
+            Statement::Impl { name, methods } => {
+                self.use_name(name);
+                methods.iter().for_each(|method| self.function(method));
+            }
+            Statement::Import { path, alias } => {
+                let name = alias.clone().unwrap_or_else(|| module_name(path));
+                self.declare(&name, Kind::Other);
+            }
+            Statement::FromImport { names, .. } => {
+                names
+                    .iter()
+                    .for_each(|name| self.declare(name, Kind::Other));
+            }
+            Statement::Export(declaration) => self.statement(declaration),
+            Statement::ExportNames(names) => names.iter().for_each(|name| self.use_name(name)),
+            Statement::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.scoped_statements(body);
+                if let Some(catch) = catch {
+                    self.push_scope();
+                    if let Some(name) = &catch.name {
+                        self.declare(name, Kind::Other);
+                    }
+                    self.statements(&catch.body);
+                    self.pop_scope();
+                }
+                if let Some(finally) = finally {
+                    self.scoped_statements(finally);
+                }
+            }
+            Statement::Located { line, statement } => {
+                self.line = *line;
+                self.statement(statement)
+            }
+            Statement::Thread(statement) => self.statement(statement),
+            Statement::Select(arms) => {
+                for arm in arms {
+                    match arm {
+                        SelectArm::Receive {
+                            channel,
+                            binding,
+                            body,
+                        } => {
+                            self.expression(channel);
+                            self.push_scope();
+                            if let Some(binding) = binding {
+                                self.declare(binding, Kind::Other);
+                            }
+                            self.statement(body);
+                            self.pop_scope();
+                        }
+                        SelectArm::Timeout { duration, body } => {
+                            self.expression(duration);
+                            self.statement(body);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn function(&mut self, declaration: &FunctionDeclaration) {
+        self.push_scope();
+        for parameter in &declaration.parameters {
+            self.declare(&parameter.name, Kind::Parameter);
+        }
+        self.statements(&declaration.body);
+        self.pop_scope();
+    }
+
+    fn check_condition(&mut self, condition: &Expression) {
+        if let Some(value) = constant(condition) {
+            self.report(
+                Rule::ConstantCondition,
+                format!("Condition is always {}", value),
+            );
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::StringLiteral(_)
+            | Expression::NumberLiteral(_)
+            | Expression::BooleanLiteral(_)
+            | Expression::This
+            | Expression::Super(_)
+            | Expression::Nil => {}
+            Expression::Identifier(name) => self.use_name(name),
+            Expression::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                if is_comparison(operator) {
+                    if let (Some(left_type), Some(right_type)) =
+                        (literal_type(left), literal_type(right))
+                    {
+                        if left_type != right_type {
+                            self.report(
+                                Rule::MismatchedComparison,
+                                format!("Comparison between {} and {}", left_type, right_type),
+                            );
+                        }
+                    }
+                }
+                self.expression(left);
+                self.expression(right);
+            }
+            Expression::Unary { right, .. } => self.expression(right),
+            Expression::Call { callee, arguments } => {
+                self.expression(callee);
+                arguments
+                    .iter()
+                    .for_each(|argument| self.expression(argument));
+            }
+            Expression::Get { object, .. }
+            | Expression::Is { value: object, .. }
+            | Expression::Propagate(object)
+            | Expression::Increment(object)
+            | Expression::Decrement(object) => self.expression(object),
+            Expression::Index { object, index } => {
+                self.expression(object);
+                self.expression(index);
+            }
+            Expression::List(elements) => {
+                elements.iter().for_each(|element| self.expression(element));
+            }
+            Expression::StructLiteral { name, fields } => {
+                self.use_name(name);
+                fields.iter().for_each(|(_, value)| self.expression(value));
+            }
+            Expression::Function(declaration) => self.function(declaration),
+            Expression::Match { value, arms } => {
+                self.expression(value);
+                for arm in arms {
+                    let mut bindings = Vec::new();
+                    pattern_bindings(&arm.pattern, &mut bindings);
+
+                    self.push_scope();
+                    bindings
+                        .iter()
+                        .for_each(|binding| self.declare(binding, Kind::Other));
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard);
+                    }
+                    self.expression(&arm.body);
+                    self.pop_scope();
+                }
+            }
+            Expression::Block(statements) => self.scoped_statements(statements),
+            Expression::InlineIf {
+                condition,
+                then_branch,
+                elif_branches,
+                else_branch,
+                ..
+            } => {
+                self.check_condition(condition);
+                self.expression(condition);
+                self.expression(then_branch);
+                for (condition, branch) in elif_branches {
+                    self.check_condition(condition);
+                    self.expression(condition);
+                    self.expression(branch);
+                }
+                self.expression(else_branch);
+            }
+        }
+    }
+}
+
+// The keyword that makes every statement after this one unreachable.
+fn exits(statement: &Statement) -> Option<&'static str> {
+    match statement {
+        Statement::Return(_) => Some("return"),
+        Statement::Throw(_) => Some("throw"),
+        Statement::Block(statements) => statements.iter().find_map(exits),
+        Statement::Located { statement, .. } => exits(statement),
+        Statement::If {
+            then_branch,
+            else_branch: Some(else_branch),
+            ..
+        } => exits(then_branch).and(exits(else_branch)),
+        _ => None,
+    }
+}
+
+// Whether a loop body contains a `return` or `throw`, the only ways out of
+// a loop whose condition never changes.
+fn can_exit(statement: &Statement) -> bool {
+    match statement {
+        Statement::Return(_) | Statement::Throw(_) => true,
+        Statement::Block(statements) => statements.iter().any(can_exit),
+        Statement::If {
+            then_branch,
+            else_branch,
+            ..
+        } => can_exit(then_branch) || else_branch.as_deref().is_some_and(can_exit),
+        Statement::While { body, .. }
+        | Statement::Located {
+            statement: body, ..
+        } => can_exit(body),
+        Statement::Try {
+            body,
+            catch,
+            finally,
+        } => {
+            body.iter().any(can_exit)
+                || catch
+                    .as_ref()
+                    .is_some_and(|catch| catch.body.iter().any(can_exit))
+                || finally
+                    .as_ref()
+                    .is_some_and(|finally| finally.iter().any(can_exit))
+        }
+        Statement::Select(arms) => arms.iter().any(|arm| match arm {
+            SelectArm::Receive { body, .. } | SelectArm::Timeout { body, .. } => can_exit(body),
+        }),
+        _ => false,
+    }
+}
+
+fn constant(condition: &Expression) -> Option<bool> {
+    match condition {
+        Expression::BooleanLiteral(value) => Some(*value),
+        Expression::Unary {
+            operator: Operator::Not,
+            right,
+        } => constant(right).map(|value| !value),
+        _ => None,
+    }
+}
+
+fn is_comparison(operator: &Operator) -> bool {
+    matches!(
+        operator,
+        Operator::EqualEqual
+            | Operator::NotEqual
+            | Operator::LessThan
+            | Operator::LessThanEqual
+            | Operator::GreaterThan
+            | Operator::GreaterThanEqual
+    )
+}
+
+fn literal_type(expression: &Expression) -> Option<&'static str> {
+    match expression {
+        Expression::StringLiteral(_) => Some("String"),
+        Expression::NumberLiteral(_) => Some("Int"),
+        Expression::BooleanLiteral(_) => Some("Bool"),
+        Expression::Nil => Some("Nil"),
+        Expression::Unary {
+            operator: Operator::Minus,
+            right,
+        } => literal_type(right).filter(|&name| name == "Int"),
+        _ => None,
+    }
+}
+
+// Whether two assignment targets are the same variable or `this`.
+fn same_place(a: &Expression, b: &Expression) -> bool {
+    match (a, b) {
+        (Expression::Identifier(a), Expression::Identifier(b)) => a == b,
+        (Expression::This, Expression::This) => true,
+        (
+            Expression::Get {
+                object: a,
+                name: a_name,
+            },
+            Expression::Get {
+                object: b,
+                name: b_name,
+            },
+        ) => a_name == b_name && same_place(a, b),
+        _ => false,
+    }
+}
@@ -0,0 +1,5 @@
+pub mod config;
+#[allow(clippy::module_inception)]
+pub mod linter;
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,128 @@
+use crate::linter::config::{LintConfig, Rule};
+use crate::linter::linter::lint;
+use crate::runtime::lexer::lexer::{lexer, lexer_with_lines};
+use crate::runtime::parser::parser::Parser;
+
+fn messages(source: &str, config: &LintConfig) -> Vec<(Rule, String)> {
+    let statements = Parser::new(lexer(source)).parse().unwrap();
+    lint(&statements, config)
+        .into_iter()
+        .map(|diagnostic| (diagnostic.rule, diagnostic.message))
+        .collect()
+}
+
+fn rules(source: &str) -> Vec<Rule> {
+    messages(source, &LintConfig::default())
+        .into_iter()
+        .map(|(rule, _)| rule)
+        .collect()
+}
+
+#[test]
+fn unused_variables_and_parameters() {
+    assert_eq!(
+        messages(
+            "func f(a, b) { var c = 1; return a; } f(1, 2);",
+            &LintConfig::default()
+        ),
+        vec![
+            (
+                Rule::UnusedVariable,
+                "Parameter 'b' is never used".to_string()
+            ),
+            (
+                Rule::UnusedVariable,
+                "Variable 'c' is never used".to_string()
+            ),
+        ]
+    );
+    assert!(rules("export var shared = 2;").is_empty());
+}
+
+#[test]
+fn globals_read_by_earlier_functions_are_used() {
+    assert!(rules("func f() { return limit; } var limit = 1; print(f());").is_empty());
+}
+
+#[test]
+fn shadowing() {
+    assert_eq!(
+        rules("var x = 1; func f(x) { return x; } print(f(x));"),
+        vec![Rule::Shadowing]
+    );
+}
+
+#[test]
+fn unreachable_code() {
+    assert_eq!(
+        rules("func f() { return 1; print(2); print(3); } print(f());"),
+        vec![Rule::UnreachableCode]
+    );
+    assert_eq!(
+        rules("func f(a) { if a { throw 1; } else { return 2; } print(a); } print(f(1));"),
+        vec![Rule::UnreachableCode]
+    );
+}
+
+#[test]
+fn constant_conditions() {
+    assert_eq!(
+        rules("while true { print(1); }"),
+        vec![Rule::ConstantCondition]
+    );
+    assert!(rules("func f() { while true { return 1; } } print(f());").is_empty());
+    assert_eq!(
+        rules("if not false { print(1); }"),
+        vec![Rule::ConstantCondition]
+    );
+}
+
+#[test]
+fn mismatched_comparisons() {
+    assert_eq!(
+        messages("print(\"a\" < 1);", &LintConfig::default()),
+        vec![(
+            Rule::MismatchedComparison,
+            "Comparison between String and Int".to_string()
+        )]
+    );
+    assert!(rules("print(1 < -2);").is_empty());
+}
+
+#[test]
+fn self_assignment() {
+    assert_eq!(
+        rules("var x = 1; x = x; print(x);"),
+        vec![Rule::SelfAssignment]
+    );
+    assert_eq!(
+        rules("struct P { x } var p = P { x: 1 }; p.x = p.x;"),
+        vec![Rule::SelfAssignment]
+    );
+}
+
+#[test]
+fn rules_can_be_disabled() {
+    let config = LintConfig::parse("[lint]\nunused-variable = false\n").unwrap();
+    assert!(messages("var x = 1;", &config).is_empty());
+    assert!(LintConfig::parse("[lint]\nno-such-rule = true\n").is_err());
+}
+
+#[test]
+fn diagnostics_carry_the_line_of_the_problem() {
+    let (tokens, lines) =
+        lexer_with_lines("func f(a) {\n    return 1;\n    print(2);\n}\nvar x = 2;\nf(1);\n");
+    let statements = Parser::with_lines(tokens, lines).parse().unwrap();
+    let lines: Vec<(Rule, usize)> = lint(&statements, &LintConfig::default())
+        .into_iter()
+        .map(|diagnostic| (diagnostic.rule, diagnostic.line))
+        .collect();
+    assert_eq!(
+        lines,
+        vec![
+            (Rule::UnreachableCode, 3),
+            (Rule::UnusedVariable, 1),
+            (Rule::UnusedVariable, 5),
+        ]
+    );
+}
@@ -0,0 +1,234 @@
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::runtime::lexer::lexer::{lexer, lossless};
+use crate::runtime::lexer::token::Token;
+use crate::runtime::parser::parser::Parser;
+use crate::runtime::parser::symbols::{Declaration, SymbolKind, Symbols};
+
+pub const KEYWORDS: &[&str] = &[
+    "var",
+    "let",
+    "mut",
+    "const",
+    "func",
+    "return",
+    "if",
+    "elif",
+    "else",
+    "while",
+    "match",
+    "struct",
+    "impl",
+    "class",
+    "extends",
+    "enum",
+    "import",
+    "from",
+    "export",
+    "as",
+    "throw",
+    "try",
+    "catch",
+    "finally",
+    "thread",
+    "test",
+    "select",
+    "print",
+    "this",
+    "super",
+    "is",
+    "instanceof",
+    "not",
+    "and",
+    "or",
+    "true",
+    "false",
+    "nil",
+    "String",
+    "Int",
+    "Bool",
+];
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub span: Range<usize>,
+    pub message: String,
+    pub error: bool,
+}
+
+pub enum Completion<'a> {
+    Keyword(&'static str),
+    Symbol(&'a Declaration),
+}
+
+/// What the editor needs to know about one document: its problems and
+/// where each name is declared and used. Rebuilt on every change.
+pub struct Analysis {
+    tokens: Vec<Token>,
+    spans: Vec<Range<usize>>,
+    symbols: Symbols,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Analysis {
+    pub fn new(source: &str) -> Self {
+        let mut tokens = Vec::new();
+        let mut spans = Vec::new();
+        let mut diagnostics = Vec::new();
+
+        for (token, span) in lossless(source) {
+            match token {
+                Some(Token::Comment) => {}
+                Some(token) => {
+                    tokens.push(token);
+                    spans.push(span);
+                }
+                None => diagnostics.push(Diagnostic {
+                    message: format!("Unexpected character '{}'", &source[span.clone()]),
+                    span,
+                    error: true,
+                }),
+            }
+        }
+
+        let mut parser = Parser::new(tokens.clone());
+        if let Err(message) = parser.parse() {
+            // The parser stops either on the offending token or just after
+            // it, so both are covered.
+            let position = parser.position();
+            let start = spans
+                .get(position.saturating_sub(1))
+                .map_or(source.len(), |span| span.start);
+            let end = spans.get(position).map_or(source.len(), |span| span.end);
+            let span = start..end.max(start);
+            diagnostics.push(Diagnostic {
+                span,
+                message,
+                error: true,
+            });
+        }
+        for warning in parser.warnings() {
+            diagnostics.push(Diagnostic {
+                span: 0..0,
+                message: warning.clone(),
+                error: false,
+            });
+        }
+
+        Analysis {
+            tokens,
+            spans,
+            symbols: parser.into_symbols(),
+            diagnostics,
+        }
+    }
+
+    pub fn span(&self, declaration: &Declaration) -> Range<usize> {
+        self.spans[declaration.token].clone()
+    }
+
+    fn token_at(&self, offset: usize) -> Option<usize> {
+        self.spans
+            .iter()
+            .position(|span| span.start <= offset && offset <= span.end)
+    }
+
+    fn declaration_index(&self, offset: usize) -> Option<usize> {
+        let token = self.token_at(offset)?;
+        self.symbols
+            .declarations
+            .iter()
+            .position(|declaration| declaration.token == token)
+            .or_else(|| {
+                self.symbols
+                    .references
+                    .iter()
+                    .find(|reference| reference.token == token)
+                    .map(|reference| reference.declaration)
+            })
+    }
+
+    /// The declaration of the name at `offset`.
+    pub fn definition(&self, offset: usize) -> Option<&Declaration> {
+        self.declaration_index(offset)
+            .map(|index| &self.symbols.declarations[index])
+    }
+
+    /// Every place the name at `offset` is used, optionally including its
+    /// declaration.
+    pub fn references(&self, offset: usize, include_declaration: bool) -> Vec<Range<usize>> {
+        let Some(index) = self.declaration_index(offset) else {
+            return Vec::new();
+        };
+
+        let mut spans = Vec::new();
+        if include_declaration {
+            spans.push(self.span(&self.symbols.declarations[index]));
+        }
+        spans.extend(
+            self.symbols
+                .references
+                .iter()
+                .filter(|reference| reference.declaration == index)
+                .map(|reference| self.spans[reference.token].clone()),
+        );
+        spans
+    }
+
+    pub fn hover(&self, offset: usize) -> Option<(String, Range<usize>)> {
+        let declaration = self.definition(offset)?;
+        let span = self.spans[self.token_at(offset)?].clone();
+        Some((declaration.detail.clone(), span))
+    }
+
+    /// The spans to replace to rename the name at `offset`.
+    pub fn rename(&self, offset: usize, new_name: &str) -> Result<Vec<Range<usize>>, String> {
+        if !matches!(lexer(new_name).as_slice(), [Token::Identifier(_)]) {
+            return Err(format!("'{}' is not a valid name", new_name));
+        }
+        let declaration = self
+            .definition(offset)
+            .ok_or("There is no symbol to rename here")?;
+        if !matches!(self.tokens[declaration.token], Token::Identifier(_)) {
+            return Err(format!("'{}' cannot be renamed", declaration.name));
+        }
+        Ok(self.references(offset, true))
+    }
+
+    /// Keywords and the names in scope at `offset`, innermost first.
+    pub fn completions(&self, offset: usize) -> Vec<Completion<'_>> {
+        let position = self.spans.partition_point(|span| span.start < offset);
+
+        let mut visible: Vec<&Declaration> = self
+            .symbols
+            .declarations
+            .iter()
+            .filter(|declaration| {
+                let scope = &self.symbols.scopes[declaration.scope];
+                declaration.kind != SymbolKind::Method
+                    && scope.start <= position
+                    && position <= scope.end
+                    && (declaration.token < position || declaration.scope == 0)
+            })
+            .collect();
+        visible.sort_by_key(|declaration| std::cmp::Reverse(declaration.token));
+
+        let mut seen = HashSet::new();
+        let mut completions: Vec<Completion> = visible
+            .into_iter()
+            .filter(|declaration| seen.insert(declaration.name.as_str()))
+            .map(Completion::Symbol)
+            .collect();
+        completions.extend(KEYWORDS.iter().map(|keyword| Completion::Keyword(keyword)));
+        completions
+    }
+
+    /// The functions, types and top-level names declared in the document.
+    pub fn document_symbols(&self) -> impl Iterator<Item = &Declaration> {
+        self.symbols
+            .declarations
+            .iter()
+            .filter(|declaration| declaration.scope == 0 || declaration.kind == SymbolKind::Method)
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+
+use serde_json::{json, Value};
+
+use crate::lsp::analysis::{Analysis, Completion};
+use crate::lsp::text::LineIndex;
+use crate::runtime::parser::symbols::SymbolKind;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const REQUEST_FAILED: i64 = -32803;
+
+struct Document {
+    text: String,
+    analysis: Analysis,
+}
+
+/// A language server for one editor session. Documents are synced in
+/// full on every change.
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shut_down: bool,
+    exit_code: Option<i32>,
+}
+
+/// Serves requests read from `input` until the client sends `exit`,
+/// returning the process exit code.
+pub fn serve(mut input: impl BufRead, mut output: impl Write) -> io::Result<i32> {
+    let mut server = Server::default();
+
+    while let Some(message) = read_message(&mut input)? {
+        for reply in server.handle(message) {
+            write_message(&mut output, &reply)?;
+        }
+        if let Some(code) = server.exit_code {
+            return Ok(code);
+        }
+    }
+    Ok(1)
+}
+
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length header")
+    })?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    // A malformed message is ignored like an unknown notification.
+    Ok(Some(serde_json::from_slice(&body).unwrap_or(Value::Null)))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+impl Server {
+    /// Handles one message, returning the responses and notifications to
+    /// send back.
+    pub fn handle(&mut self, message: Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default().to_string();
+        let params = &message["params"];
+        let Some(id) = message.get("id").cloned() else {
+            return self.notification(&method, params);
+        };
+
+        let result = match method.as_str() {
+            "initialize" => Ok(capabilities()),
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Value::Null)
+            }
+            "textDocument/hover" => self.hover(params),
+            "textDocument/definition" => self.definition(params),
+            "textDocument/references" => self.references(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            "textDocument/completion" => self.completion(params),
+            "textDocument/rename" => self.rename(params),
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method '{}'", method))),
+        };
+
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        vec![response]
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+
+        match method {
+            "exit" => {
+                self.exit_code = Some(if self.shut_down { 0 } else { 1 });
+                Vec::new()
+            }
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.update(uri, text.to_string())
+            }
+            "textDocument/didChange" => {
+                let changes = params["contentChanges"].as_array();
+                match changes.and_then(|changes| changes.last()) {
+                    Some(change) => {
+                        let text = change["text"].as_str().unwrap_or_default();
+                        self.update(uri, text.to_string())
+                    }
+                    None => Vec::new(),
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                vec![publish_diagnostics(&uri, Vec::new())]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn update(&mut self, uri: String, text: String) -> Vec<Value> {
+        let analysis = Analysis::new(&text);
+        let index = LineIndex::new(&text);
+        let diagnostics = analysis
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                json!({
+                    "range": range(&index, &diagnostic.span),
+                    "severity": if diagnostic.error { 1 } else { 2 },
+                    "source": "luth",
+                    "message": diagnostic.message,
+                })
+            })
+            .collect();
+
+        let notification = publish_diagnostics(&uri, diagnostics);
+        self.documents.insert(uri, Document { text, analysis });
+        vec![notification]
+    }
+
+    // The document a request is about and the byte offset of its position.
+    fn locate<'a>(
+        &'a self,
+        params: &'a Value,
+    ) -> Result<(&'a str, &'a Document, usize), (i64, String)> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let document = self
+            .documents
+            .get(uri)
+            .ok_or((INVALID_PARAMS, format!("Unknown document '{}'", uri)))?;
+
+        let position = &params["position"];
+        let line = position["line"].as_u64().unwrap_or_default() as u32;
+        let character = position["character"].as_u64().unwrap_or_default() as u32;
+        let offset = LineIndex::new(&document.text).offset(line, character);
+        Ok((uri, document, offset))
+    }
+
+    fn hover(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (_, document, offset) = self.locate(params)?;
+        let index = LineIndex::new(&document.text);
+
+        Ok(match document.analysis.hover(offset) {
+            Some((detail, span)) => json!({
+                "contents": {
+                    "kind": "markdown",
+                    "value": format!("```luth\n{}\n```", detail),
+                },
+                "range": range(&index, &span),
+            }),
+            None => Value::Null,
+        })
+    }
+
+    fn definition(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (uri, document, offset) = self.locate(params)?;
+        let index = LineIndex::new(&document.text);
+
+        Ok(match document.analysis.definition(offset) {
+            Some(declaration) => location(uri, &index, &document.analysis.span(declaration)),
+            None => Value::Null,
+        })
+    }
+
+    fn references(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (uri, document, offset) = self.locate(params)?;
+        let index = LineIndex::new(&document.text);
+        let include_declaration = params["context"]["includeDeclaration"]
+            .as_bool()
+            .unwrap_or(true);
+
+        let locations = document
+            .analysis
+            .references(offset, include_declaration)
+            .iter()
+            .map(|span| location(uri, &index, span))
+            .collect();
+        Ok(Value::Array(locations))
+    }
+
+    fn document_symbols(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let document = self
+            .documents
+            .get(uri)
+            .ok_or((INVALID_PARAMS, format!("Unknown document '{}'", uri)))?;
+        let index = LineIndex::new(&document.text);
+
+        let symbols = document
+            .analysis
+            .document_symbols()
+            .map(|declaration| {
+                let mut symbol = json!({
+                    "name": declaration.name,
+                    "kind": symbol_kind(declaration.kind),
+                    "location": location(uri, &index, &document.analysis.span(declaration)),
+                });
+                if let Some(container) = &declaration.container {
+                    symbol["containerName"] = json!(container);
+                }
+                symbol
+            })
+            .collect();
+        Ok(Value::Array(symbols))
+    }
+
+    fn completion(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (_, document, offset) = self.locate(params)?;
+
+        let items = document
+            .analysis
+            .completions(offset)
+            .into_iter()
+            .map(|completion| match completion {
+                Completion::Keyword(keyword) => json!({ "label": keyword, "kind": 14 }),
+                Completion::Symbol(declaration) => json!({
+                    "label": declaration.name,
+                    "kind": completion_kind(declaration.kind),
+                    "detail": declaration.detail,
+                }),
+            })
+            .collect();
+        Ok(Value::Array(items))
+    }
+
+    fn rename(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (uri, document, offset) = self.locate(params)?;
+        let index = LineIndex::new(&document.text);
+        let new_name = params["newName"].as_str().unwrap_or_default();
+
+        let spans = document
+            .analysis
+            .rename(offset, new_name)
+            .map_err(|error| (REQUEST_FAILED, error))?;
+        let edits: Vec<Value> = spans
+            .iter()
+            .map(|span| json!({ "range": range(&index, span), "newText": new_name }))
+            .collect();
+        Ok(json!({ "changes": { uri: edits } }))
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": 1,
+            "hoverProvider": true,
+            "definitionProvider": true,
+            "referencesProvider": true,
+            "documentSymbolProvider": true,
+            "completionProvider": {},
+            "renameProvider": true,
+        },
+        "serverInfo": { "name": "luth", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+fn range(index: &LineIndex, span: &Range<usize>) -> Value {
+    let (start_line, start_character) = index.position(span.start);
+    let (end_line, end_character) = index.position(span.end);
+    json!({
+        "start": { "line": start_line, "character": start_character },
+        "end": { "line": end_line, "character": end_character },
+    })
+}
+
+fn location(uri: &str, index: &LineIndex, span: &Range<usize>) -> Value {
+    json!({ "uri": uri, "range": range(index, span) })
+}
+
+// LSP `SymbolKind` numbers.
+fn symbol_kind(kind: SymbolKind) -> u8 {
+    match kind {
+        SymbolKind::Module => 2,
+        SymbolKind::Class => 5,
+        SymbolKind::Method => 6,
+        SymbolKind::Enum => 10,
+        SymbolKind::Function => 12,
+        SymbolKind::Variable | SymbolKind::Parameter => 13,
+        SymbolKind::Constant => 14,
+        SymbolKind::Struct => 23,
+    }
+}
+
+// LSP `CompletionItemKind` numbers.
+fn completion_kind(kind: SymbolKind) -> u8 {
+    match kind {
+        SymbolKind::Method => 2,
+        SymbolKind::Function => 3,
+        SymbolKind::Variable | SymbolKind::Parameter => 6,
+        SymbolKind::Class => 7,
+        SymbolKind::Module => 9,
+        SymbolKind::Enum => 13,
+        SymbolKind::Constant => 21,
+        SymbolKind::Struct => 22,
+    }
+}
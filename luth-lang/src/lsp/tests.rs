@@ -0,0 +1,171 @@
+use serde_json::{json, Value};
+
+use crate::lsp::analysis::{Analysis, Completion};
+use crate::lsp::server::{read_message, serve, write_message};
+use crate::lsp::text::LineIndex;
+
+const SOURCE: &str = "\
+struct Point { x: Int, y: Int }
+
+func area(p: Point, scale) {
+    let size = p.x * p.y;
+    return size * scale;
+}
+
+var total = area(Point { x: 2, y: 3 }, 2);
+print(total);
+";
+
+fn offset(text: &str, needle: &str, occurrence: usize) -> usize {
+    text.match_indices(needle).nth(occurrence).unwrap().0
+}
+
+fn texts(spans: Vec<std::ops::Range<usize>>) -> Vec<usize> {
+    spans.into_iter().map(|span| span.start).collect()
+}
+
+#[test]
+fn definition_and_references() {
+    let analysis = Analysis::new(SOURCE);
+    let declaration = analysis.definition(offset(SOURCE, "size", 1)).unwrap();
+    assert_eq!(declaration.name, "size");
+    assert_eq!(analysis.span(declaration).start, offset(SOURCE, "size", 0));
+
+    assert_eq!(
+        texts(analysis.references(offset(SOURCE, "total", 0), true)),
+        vec![offset(SOURCE, "total", 0), offset(SOURCE, "total", 1)]
+    );
+    assert_eq!(
+        texts(analysis.references(offset(SOURCE, "Point", 0), false)),
+        vec![offset(SOURCE, "Point", 1), offset(SOURCE, "Point", 2)]
+    );
+}
+
+#[test]
+fn hover_shows_declared_and_inferred_types() {
+    let analysis = Analysis::new(SOURCE);
+    let hover = |needle, occurrence| {
+        analysis
+            .hover(offset(SOURCE, needle, occurrence))
+            .unwrap()
+            .0
+    };
+    assert_eq!(hover("area", 1), "func area(p: Point, scale)");
+    assert_eq!(hover("size", 1), "let size");
+    assert_eq!(hover("p.x", 0), "p: Point");
+
+    let analysis = Analysis::new("const LIMIT = 10; var name = \"a\" + \"b\";");
+    assert_eq!(analysis.hover(7).unwrap().0, "const LIMIT: Int");
+    assert_eq!(analysis.hover(23).unwrap().0, "var name: String");
+}
+
+#[test]
+fn completion_only_offers_names_in_scope() {
+    let analysis = Analysis::new(SOURCE);
+    let names = |offset| -> Vec<String> {
+        analysis
+            .completions(offset)
+            .into_iter()
+            .filter_map(|completion| match completion {
+                Completion::Symbol(declaration) => Some(declaration.name.clone()),
+                Completion::Keyword(_) => None,
+            })
+            .collect()
+    };
+
+    let inside = names(offset(SOURCE, "return", 0));
+    for name in ["size", "scale", "p", "area", "Point", "total"] {
+        assert!(inside.contains(&name.to_string()), "{} missing", name);
+    }
+    let outside = names(offset(SOURCE, "print", 0));
+    assert!(!outside.contains(&"size".to_string()));
+    assert!(outside.contains(&"total".to_string()));
+}
+
+#[test]
+fn rename_covers_every_use() {
+    let analysis = Analysis::new(SOURCE);
+    assert_eq!(
+        texts(
+            analysis
+                .rename(offset(SOURCE, "scale", 1), "factor")
+                .unwrap()
+        ),
+        vec![offset(SOURCE, "scale", 0), offset(SOURCE, "scale", 1)]
+    );
+    assert!(analysis
+        .rename(offset(SOURCE, "scale", 0), "while")
+        .is_err());
+}
+
+#[test]
+fn forward_references_resolve_to_globals() {
+    let source = "func f() { return g(); } func g() { return 1; }";
+    let analysis = Analysis::new(source);
+    let declaration = analysis.definition(offset(source, "g", 0)).unwrap();
+    assert_eq!(analysis.span(declaration).start, offset(source, "g", 1));
+}
+
+#[test]
+fn diagnostics_point_at_the_problem() {
+    let source = "var x = 1;\nvar = 2;\n";
+    let analysis = Analysis::new(source);
+    assert_eq!(analysis.diagnostics.len(), 1);
+    assert_eq!(analysis.diagnostics[0].message, "Expected variable name");
+    let span = &analysis.diagnostics[0].span;
+    assert_eq!(&source[span.clone()], "= 2");
+}
+
+#[test]
+fn line_index_counts_utf16() {
+    let index = LineIndex::new("a\n\"é😀\" b");
+    assert_eq!(index.position(9), (1, 4));
+    assert_eq!(index.offset(1, 4), 9);
+    assert_eq!(index.offset(5, 0), 12);
+}
+
+#[test]
+fn serves_requests_over_stdio() {
+    let uri = "file:///main.lt";
+    let messages = [
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": uri, "text": SOURCE } },
+        }),
+        json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "textDocument/definition",
+            "params": {
+                "textDocument": { "uri": uri },
+                "position": { "line": 8, "character": 7 },
+            },
+        }),
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" }),
+        json!({ "jsonrpc": "2.0", "method": "exit" }),
+    ];
+    let mut input = Vec::new();
+    for message in &messages {
+        write_message(&mut input, message).unwrap();
+    }
+
+    let mut output = Vec::new();
+    assert_eq!(serve(input.as_slice(), &mut output).unwrap(), 0);
+
+    let mut reader = output.as_slice();
+    let mut replies: Vec<Value> = Vec::new();
+    while let Some(reply) = read_message(&mut reader).unwrap() {
+        replies.push(reply);
+    }
+    assert_eq!(replies.len(), 4);
+    assert_eq!(replies[0]["result"]["capabilities"]["renameProvider"], true);
+    assert_eq!(replies[1]["method"], "textDocument/publishDiagnostics");
+    assert_eq!(replies[1]["params"]["diagnostics"], json!([]));
+    assert_eq!(
+        replies[2]["result"]["range"]["start"],
+        json!({ "line": 7, "character": 4 })
+    );
+    assert_eq!(replies[3]["result"], Value::Null);
+}
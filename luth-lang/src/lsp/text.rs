@@ -0,0 +1,40 @@
+/// Converts between byte offsets and LSP positions, which count lines and
+/// UTF-16 code units within a line.
+pub struct LineIndex<'a> {
+    text: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(text: &'a str) -> Self {
+        let mut starts = vec![0];
+        starts.extend(text.match_indices('\n').map(|(offset, _)| offset + 1));
+        LineIndex { text, starts }
+    }
+
+    pub fn position(&self, offset: usize) -> (u32, u32) {
+        let offset = offset.min(self.text.len());
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let character = self.text[self.starts[line]..offset].encode_utf16().count();
+        (line as u32, character as u32)
+    }
+
+    pub fn offset(&self, line: u32, character: u32) -> usize {
+        let Some(&start) = self.starts.get(line as usize) else {
+            return self.text.len();
+        };
+        let end = self
+            .starts
+            .get(line as usize + 1)
+            .map_or(self.text.len(), |next| next - 1);
+
+        let mut units = 0;
+        for (index, c) in self.text[start..end].char_indices() {
+            if units >= character as usize {
+                return start + index;
+            }
+            units += c.len_utf16();
+        }
+        end
+    }
+}
@@ -1,5 +1,11 @@
 mod cli;
+mod dap;
+mod formatter;
+mod linter;
+mod lsp;
+mod project;
 mod runtime;
+mod testing;
 
 fn main() {
     cli::app::init_cli();
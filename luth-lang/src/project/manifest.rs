@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+/// The project manifest, looked for in the working directory. Each tool
+/// reads its own table from it, like `[lint]` and `[format]`.
+pub const MANIFEST_FILE: &str = "luth.toml";
+
+/// The `[package]` and `[dependencies]` tables of a manifest. A manifest
+/// holding only tool settings has no package.
+#[derive(Debug, Deserialize)]
+pub struct Manifest {
+    pub package: Option<Package>,
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, Dependency>,
+    // The directory the manifest is in, which its paths are relative to.
+    #[serde(skip)]
+    pub root: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    // The file `luth run` runs when given none.
+    #[serde(default = "default_entry")]
+    pub entry: String,
+    // Directories searched for imported modules.
+    #[serde(default = "default_source_dirs")]
+    pub source_dirs: Vec<String>,
+}
+
+/// A project on disk whose modules are imported as `name/module`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dependency {
+    pub path: String,
+}
+
+fn default_entry() -> String {
+    "src/main.lt".to_string()
+}
+
+fn default_source_dirs() -> Vec<String> {
+    vec!["src".to_string()]
+}
+
+impl Manifest {
+    /// The manifest in the working directory, if there is one.
+    pub fn find() -> Result<Option<Manifest>, String> {
+        let path = Path::new(MANIFEST_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        Manifest::load(path).map(Some)
+    }
+
+    pub fn load(path: &Path) -> Result<Manifest, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|error| format!("Could not read '{}': {}", path.display(), error))?;
+        let mut manifest =
+            Manifest::parse(&source).map_err(|error| format!("{}: {}", path.display(), error))?;
+        manifest.root = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        Ok(manifest)
+    }
+
+    pub fn parse(source: &str) -> Result<Manifest, String> {
+        let manifest: Manifest =
+            toml::from_str(source).map_err(|error| error.message().to_string())?;
+
+        if let Some(package) = &manifest.package {
+            if !is_valid_name(&package.name) {
+                return Err(format!("Invalid package name '{}'", package.name));
+            }
+            let parts: Vec<&str> = package.version.split('.').collect();
+            let numeric = parts.iter().all(|part| part.parse::<u64>().is_ok());
+            if parts.len() != 3 || !numeric {
+                return Err(format!(
+                    "Invalid version '{}', expected MAJOR.MINOR.PATCH",
+                    package.version
+                ));
+            }
+        }
+        for name in manifest.dependencies.keys() {
+            if !is_valid_name(name) {
+                return Err(format!("Invalid dependency name '{}'", name));
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// The file to run for the package, relative to the working directory.
+    pub fn entry(&self) -> Option<PathBuf> {
+        let package = self.package.as_ref()?;
+        Some(self.root.join(&package.entry))
+    }
+
+    /// The directories modules are imported from. Without a package, that
+    /// is the manifest's own directory.
+    pub fn source_dirs(&self) -> Vec<PathBuf> {
+        match &self.package {
+            Some(package) => package
+                .source_dirs
+                .iter()
+                .map(|dir| self.root.join(dir))
+                .collect(),
+            None => vec![self.root.clone()],
+        }
+    }
+
+    /// The source directories of each dependency, by name. A dependency
+    /// without a readable manifest of its own is imported from its root.
+    pub fn packages(&self) -> BTreeMap<String, Vec<PathBuf>> {
+        self.dependencies
+            .iter()
+            .map(|(name, dependency)| {
+                let root = self.root.join(&dependency.path);
+                let dirs = match Manifest::load(&root.join(MANIFEST_FILE)) {
+                    Ok(manifest) => manifest.source_dirs(),
+                    Err(_) => vec![root],
+                };
+                (name.clone(), dirs)
+            })
+            .collect()
+    }
+}
+
+/// Whether `name` can name a package: a letter followed by letters, digits,
+/// `_` and `-`.
+pub fn is_valid_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
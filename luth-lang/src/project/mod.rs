@@ -0,0 +1,4 @@
+pub mod manifest;
+pub mod scaffold;
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,83 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::project::manifest::{is_valid_name, MANIFEST_FILE};
+
+/// Creates the files of a new project called `name` in `dir`, creating the
+/// directory if needed, and returns the ones it wrote. Existing files are
+/// left alone, except that a directory that already has a manifest is an
+/// error.
+pub fn scaffold(dir: &Path, name: &str) -> Result<Vec<PathBuf>, String> {
+    if !is_valid_name(name) {
+        return Err(format!("Invalid package name '{}'", name));
+    }
+    if dir.join(MANIFEST_FILE).exists() {
+        return Err(format!(
+            "'{}' already has a {}",
+            dir.display(),
+            MANIFEST_FILE
+        ));
+    }
+
+    let files = [
+        (MANIFEST_FILE, manifest(name)),
+        ("src/main.lt", MAIN.replace("{name}", name)),
+        ("src/calc.lt", CALC.to_string()),
+        ("src/calc_test.lt", CALC_TEST.to_string()),
+        (".gitignore", "lcov.info\n".to_string()),
+    ];
+
+    let mut written = Vec::new();
+    for (file, contents) in files {
+        let path = dir.join(file);
+        if path.exists() {
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|error| format!("Could not create '{}': {}", parent.display(), error))?;
+        }
+        fs::write(&path, contents)
+            .map_err(|error| format!("Could not write '{}': {}", path.display(), error))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+fn manifest(name: &str) -> String {
+    format!(
+        "[package]
+name = \"{}\"
+version = \"0.1.0\"
+entry = \"src/main.lt\"
+source-dirs = [\"src\"]
+
+[dependencies]
+
+[lint]
+
+[format]
+indent = 4
+",
+        name
+    )
+}
+
+const MAIN: &str = "from \"calc\" import square;
+
+print(\"Hello from {name}!\");
+print(square(4));
+";
+
+const CALC: &str = "export func square(n) {
+    return n * n;
+}
+";
+
+const CALC_TEST: &str = "from \"calc\" import square;
+
+test \"squares numbers\" {
+    assert_eq(square(3), 9);
+    assert_eq(square(-2), 4);
+}
+";
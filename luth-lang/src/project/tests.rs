@@ -0,0 +1,53 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::project::manifest::{Manifest, MANIFEST_FILE};
+use crate::project::scaffold::scaffold;
+
+#[test]
+fn manifest_fills_in_defaults() {
+    let manifest = Manifest::parse(
+        "[package]\nname = \"shapes\"\nversion = \"1.2.0\"\n\n[dependencies]\ngeometry = { path = \"../geometry\" }\n\n[lint]\nshadowing = false\n",
+    )
+    .unwrap();
+
+    let package = manifest.package.as_ref().unwrap();
+    assert_eq!(package.name, "shapes");
+    assert_eq!(manifest.entry(), Some(PathBuf::from("src/main.lt")));
+    assert_eq!(manifest.source_dirs(), vec![PathBuf::from("src")]);
+    assert_eq!(manifest.dependencies["geometry"].path, "../geometry");
+}
+
+#[test]
+fn settings_only_manifest_has_no_package() {
+    let manifest = Manifest::parse("[lint]\nshadowing = false\n").unwrap();
+    assert!(manifest.package.is_none());
+    assert_eq!(manifest.entry(), None);
+}
+
+#[test]
+fn rejects_invalid_manifests() {
+    let package = |fields: &str| Manifest::parse(&format!("[package]\n{}", fields));
+    assert!(package("name = \"a\"\nversion = \"1.0\"\n").is_err());
+    assert!(package("name = \"1st\"\nversion = \"1.0.0\"\n").is_err());
+    assert!(package("name = \"a\"\nversion = \"1.0.0\"\nauthor = \"me\"\n").is_err());
+    assert!(package("version = \"1.0.0\"\n").is_err());
+    assert!(Manifest::parse("[dependencies]\ngeometry = \"1.0\"\n").is_err());
+}
+
+#[test]
+fn scaffolds_a_project_once() {
+    let dir = env::temp_dir().join(format!("luth-scaffold-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    let written = scaffold(&dir, "hello").unwrap();
+    assert_eq!(written.len(), 5);
+    let manifest = Manifest::load(&dir.join(MANIFEST_FILE)).unwrap();
+    assert_eq!(manifest.package.unwrap().name, "hello");
+    assert!(dir.join("src/main.lt").is_file());
+
+    assert!(scaffold(&dir, "hello").is_err());
+    assert!(scaffold(&dir.join("other"), "not valid").is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,263 @@
+use crate::runtime::interpreter::concurrency::{Channel, SyncGroup};
+use crate::runtime::interpreter::environment::Value;
+use crate::runtime::interpreter::result;
+
+pub fn call_function(name: &str, arguments: Vec<Value>) -> Option<Result<Value, String>> {
+    let result = match name {
+        "len" => len(arguments),
+        "assert" => assert(arguments),
+        "assert_eq" => assert_equality("assert_eq", arguments, true),
+        "assert_ne" => assert_equality("assert_ne", arguments, false),
+        "type_of" => type_of(arguments),
+        "thread_ch" => thread_ch(arguments),
+        "thread_sync" => thread_sync(arguments),
+        _ => return result::construct(name, arguments),
+    };
+    Some(result)
+}
+
+pub fn call_method(object: &Value, name: &str, arguments: Vec<Value>) -> Result<Value, String> {
+    match object {
+        Value::List(elements) => list_method(&mut elements.lock().unwrap(), name, arguments),
+        Value::Channel(channel) => channel_method(channel, name, arguments),
+        Value::Sync(sync) => sync_method(sync, name, arguments),
+        _ => Err(format!(
+            "Undefined method '{}' for value '{}'",
+            name, object
+        )),
+    }
+}
+
+pub fn index(object: &Value, index: &Value) -> Result<Value, String> {
+    match object {
+        Value::List(elements) => {
+            let elements = elements.lock().unwrap();
+            let index = expect_index(index, elements.len())?;
+            Ok(elements[index].clone())
+        }
+        Value::StringLiteral(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            let index = expect_index(index, chars.len())?;
+            Ok(Value::StringLiteral(chars[index].to_string()))
+        }
+        _ => Err(format!("Cannot index into '{}'", object)),
+    }
+}
+
+fn len(arguments: Vec<Value>) -> Result<Value, String> {
+    match arguments.as_slice() {
+        [Value::List(elements)] => Ok(Value::NumberLiteral(elements.lock().unwrap().len() as f64)),
+        [Value::StringLiteral(s)] => Ok(Value::NumberLiteral(s.chars().count() as f64)),
+        [value] => Err(format!("Cannot get the length of '{}'", value)),
+        _ => Err(arity_error("len", "1", arguments.len())),
+    }
+}
+
+/// The status `exit()` ends the program with, 0 when none is given. The
+/// interpreter handles the call itself, since it unwinds everything.
+pub fn exit_status(arguments: Vec<Value>) -> Result<i32, String> {
+    match arguments.as_slice() {
+        [] => Ok(0),
+        [Value::NumberLiteral(status)] if status.fract() == 0.0 => Ok(*status as i32),
+        [value] => Err(format!("Exit status must be an integer, found '{}'", value)),
+        _ => Err(arity_error("exit", "0 or 1", arguments.len())),
+    }
+}
+
+fn type_of(arguments: Vec<Value>) -> Result<Value, String> {
+    match arguments.as_slice() {
+        [value] => Ok(Value::StringLiteral(value.type_name())),
+        _ => Err(arity_error("type_of", "1", arguments.len())),
+    }
+}
+
+fn assert(arguments: Vec<Value>) -> Result<Value, String> {
+    match arguments.as_slice() {
+        [condition] if condition.is_truthy() => Ok(Value::Nil),
+        [condition, _] if condition.is_truthy() => Ok(Value::Nil),
+        [_] => Err("Assertion failed".to_string()),
+        [_, message] => Err(format!("Assertion failed: {}", message)),
+        _ => Err(arity_error("assert", "1 or 2", arguments.len())),
+    }
+}
+
+fn assert_equality(function: &str, arguments: Vec<Value>, equal: bool) -> Result<Value, String> {
+    let (left, right, message) = match arguments.as_slice() {
+        [left, right] => (left, right, None),
+        [left, right, message] => (left, right, Some(message)),
+        _ => return Err(arity_error(function, "2 or 3", arguments.len())),
+    };
+    if (left == right) == equal {
+        return Ok(Value::Nil);
+    }
+
+    let mut failure = format!("Assertion '{}' failed", function);
+    if let Some(message) = message {
+        failure.push_str(&format!(": {}", message));
+    }
+    failure.push_str(&format!(
+        "\n  left:  {}\n  right: {}",
+        left.repr(),
+        right.repr()
+    ));
+    if equal {
+        if let Some(difference) = difference(left, right, String::new()) {
+            failure.push('\n');
+            failure.push_str(&difference);
+        }
+    }
+    Err(failure)
+}
+
+// Where two unequal values first differ, when that is more specific than
+// the values themselves.
+fn difference(left: &Value, right: &Value, path: String) -> Option<String> {
+    match (left, right) {
+        (Value::List(left), Value::List(right)) => {
+            let (left, right) = (left.lock().unwrap().clone(), right.lock().unwrap().clone());
+            let first = left.iter().zip(&right).position(|(l, r)| l != r);
+            match first {
+                Some(index) => {
+                    let path = format!("{}[{}]", path, index);
+                    difference(&left[index], &right[index], path.clone())
+                        .or_else(|| Some(at(&path, &left[index], &right[index])))
+                }
+                None => Some(format!(
+                    "  at {}: lengths differ, {} != {}",
+                    if path.is_empty() { "top level" } else { &path },
+                    left.len(),
+                    right.len()
+                )),
+            }
+        }
+        (Value::Instance(left), Value::Instance(right)) => {
+            let right = right.values();
+            left.values()
+                .into_iter()
+                .zip(right)
+                .find(|((_, l), (_, r))| l != r)
+                .and_then(|((name, l), (_, r))| {
+                    let path = format!("{}.{}", path, name);
+                    difference(&l, &r, path.clone()).or_else(|| Some(at(&path, &l, &r)))
+                })
+        }
+        // Nested strings are short enough to compare at a glance.
+        (Value::StringLiteral(left), Value::StringLiteral(right)) if path.is_empty() => {
+            let mut pairs = left.chars().zip(right.chars()).enumerate();
+            let (index, (l, r)) = pairs.find(|(_, (l, r))| l != r)?;
+            Some(format!("  at character {}: {:?} != {:?}", index, l, r))
+        }
+        _ => None,
+    }
+}
+
+fn at(path: &str, left: &Value, right: &Value) -> String {
+    format!("  at {}: {} != {}", path, left.repr(), right.repr())
+}
+
+fn thread_ch(arguments: Vec<Value>) -> Result<Value, String> {
+    let capacity = match arguments.as_slice() {
+        [] => 0,
+        [capacity] => expect_count("thread_ch", capacity)?,
+        _ => return Err(arity_error("thread_ch", "0 or 1", arguments.len())),
+    };
+    Ok(Value::Channel(Channel::new(capacity)))
+}
+
+fn thread_sync(arguments: Vec<Value>) -> Result<Value, String> {
+    let count = match arguments.as_slice() {
+        [count] => expect_count("thread_sync", count)?,
+        _ => return Err(arity_error("thread_sync", "1", arguments.len())),
+    };
+    Ok(Value::Sync(SyncGroup::new(count)))
+}
+
+fn channel_method(channel: &Channel, name: &str, arguments: Vec<Value>) -> Result<Value, String> {
+    let mut arguments = arguments.into_iter();
+    let argument_count = arguments.len();
+    let expected = match name {
+        "send" | "try_send" => 1,
+        "receive" | "try_receive" | "close" | "is_closed" | "len" | "capacity" => 0,
+        _ => return Err(format!("Undefined method '{}' for channel", name)),
+    };
+    if argument_count != expected {
+        return Err(arity_error(name, &expected.to_string(), argument_count));
+    }
+
+    match name {
+        "send" => {
+            channel.send(arguments.next().unwrap())?;
+            Ok(Value::Nil)
+        }
+        "receive" => Ok(channel.receive()),
+        "try_send" => Ok(Value::BooleanLiteral(
+            channel.try_send(arguments.next().unwrap())?,
+        )),
+        "try_receive" => Ok(channel.try_receive().unwrap_or(Value::Nil)),
+        "close" => {
+            channel.close();
+            Ok(Value::Nil)
+        }
+        "is_closed" => Ok(Value::BooleanLiteral(channel.is_closed())),
+        "len" => Ok(Value::NumberLiteral(channel.queued() as f64)),
+        _ => Ok(Value::NumberLiteral(channel.capacity() as f64)),
+    }
+}
+
+fn list_method(
+    elements: &mut Vec<Value>,
+    name: &str,
+    arguments: Vec<Value>,
+) -> Result<Value, String> {
+    match (name, arguments.len()) {
+        ("push", 1) => {
+            elements.extend(arguments);
+            Ok(Value::Nil)
+        }
+        ("pop", 0) => Ok(elements.pop().unwrap_or(Value::Nil)),
+        ("len", 0) => Ok(Value::NumberLiteral(elements.len() as f64)),
+        ("push", found) => Err(arity_error(name, "1", found)),
+        ("pop" | "len", found) => Err(arity_error(name, "0", found)),
+        _ => Err(format!("Undefined method '{}' for list", name)),
+    }
+}
+
+fn sync_method(sync: &SyncGroup, name: &str, arguments: Vec<Value>) -> Result<Value, String> {
+    if !arguments.is_empty() {
+        return Err(arity_error(name, "0", arguments.len()));
+    }
+
+    match name {
+        "ready" => sync.ready(),
+        "wait" => sync.wait(),
+        _ => return Err(format!("Undefined method '{}' for sync", name)),
+    }
+    Ok(Value::Nil)
+}
+
+fn expect_count(function: &str, value: &Value) -> Result<usize, String> {
+    match value {
+        Value::NumberLiteral(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as usize),
+        _ => Err(format!(
+            "Expected a non-negative integer in '{}', found '{}'",
+            function, value
+        )),
+    }
+}
+
+fn expect_index(index: &Value, len: usize) -> Result<usize, String> {
+    match index {
+        Value::NumberLiteral(n) if *n >= 0.0 && n.fract() == 0.0 && (*n as usize) < len => {
+            Ok(*n as usize)
+        }
+        Value::NumberLiteral(n) => Err(format!("Index {} out of bounds for length {}", n, len)),
+        _ => Err(format!("Expected a number as index, found '{}'", index)),
+    }
+}
+
+fn arity_error(function: &str, expected: &str, found: usize) -> String {
+    format!(
+        "Expected {} arguments in '{}', found {}",
+        expected, function, found
+    )
+}
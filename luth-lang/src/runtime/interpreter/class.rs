@@ -0,0 +1,95 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::runtime::interpreter::environment::Value;
+use crate::runtime::interpreter::function::Function;
+
+#[derive(Debug)]
+pub struct Class {
+    pub name: String,
+    pub superclass: Option<Arc<Class>>,
+    methods: HashMap<String, Arc<Function>>,
+    // Methods never change after the class is declared, so lookups through
+    // the superclass chain are remembered.
+    resolved: Mutex<HashMap<String, Arc<Function>>>,
+}
+
+impl Class {
+    pub fn new(
+        name: String,
+        superclass: Option<Arc<Class>>,
+        methods: HashMap<String, Arc<Function>>,
+    ) -> Self {
+        Class {
+            name,
+            superclass,
+            methods,
+            resolved: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn find_method(&self, name: &str) -> Option<Arc<Function>> {
+        if let Some(method) = self.resolved.lock().unwrap().get(name) {
+            return Some(Arc::clone(method));
+        }
+
+        let method = match self.methods.get(name) {
+            Some(method) => Arc::clone(method),
+            None => self.superclass.as_ref()?.find_method(name)?,
+        };
+        self.resolved
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), Arc::clone(&method));
+        Some(method)
+    }
+
+    /// Whether this class is `other` or inherits from it.
+    pub fn is_subclass_of(&self, other: &Class) -> bool {
+        std::ptr::eq(self, other)
+            || self
+                .superclass
+                .as_ref()
+                .is_some_and(|superclass| superclass.is_subclass_of(other))
+    }
+
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name == name
+            || self
+                .superclass
+                .as_ref()
+                .is_some_and(|superclass| superclass.is_named(name))
+    }
+}
+
+#[derive(Debug)]
+pub struct Object {
+    pub class: Arc<Class>,
+    fields: Mutex<HashMap<String, Value>>,
+}
+
+impl Object {
+    pub fn new(class: Arc<Class>) -> Self {
+        Object {
+            class,
+            fields: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Looks up a field first and then a method, bound to `this`.
+    pub fn get(this: &Arc<Object>, name: &str) -> Option<Value> {
+        if let Some(value) = this.fields.lock().unwrap().get(name) {
+            return Some(value.clone());
+        }
+
+        let method = this.class.find_method(name)?;
+        let bound = method.bind(Value::Object(Arc::clone(this)));
+        Some(Value::Function(Arc::new(bound)))
+    }
+
+    pub fn set(&self, name: String, value: Value) {
+        self.fields.lock().unwrap().insert(name, value);
+    }
+}
@@ -0,0 +1,272 @@
+use std::{
+    collections::{hash_map::RandomState, VecDeque},
+    hash::{BuildHasher, Hasher},
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::runtime::interpreter::environment::Value;
+
+// Bumped on every send and close so that a `select` waiting on several
+// channels can sleep on a single condition variable.
+static ACTIVITY: Mutex<u64> = Mutex::new(0);
+static ACTIVITY_CHANGED: Condvar = Condvar::new();
+
+fn notify_activity() {
+    let mut generation = ACTIVITY.lock().unwrap();
+    *generation = generation.wrapping_add(1);
+    ACTIVITY_CHANGED.notify_all();
+}
+
+#[derive(Debug, Clone)]
+pub struct Channel {
+    inner: Arc<ChannelInner>,
+}
+
+#[derive(Debug)]
+struct ChannelInner {
+    capacity: usize,
+    state: Mutex<ChannelState>,
+    changed: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct ChannelState {
+    // Each value with the number of the send that queued it.
+    queue: VecDeque<(u64, Value)>,
+    sent: u64,
+    received: u64,
+    waiting_receivers: usize,
+    closed: bool,
+}
+
+impl Channel {
+    /// A capacity of `0` makes an unbuffered channel, where every send
+    /// waits until a receiver has taken the value.
+    pub fn new(capacity: usize) -> Self {
+        Channel {
+            inner: Arc::new(ChannelInner {
+                capacity,
+                state: Mutex::new(ChannelState::default()),
+                changed: Condvar::new(),
+            }),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity
+    }
+
+    pub fn queued(&self) -> usize {
+        self.inner.state.lock().unwrap().queue.len()
+    }
+
+    pub fn ptr_eq(&self, other: &Channel) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    pub fn send(&self, value: Value) -> Result<(), String> {
+        let inner = &self.inner;
+        let mut state = inner.state.lock().unwrap();
+
+        if inner.capacity > 0 {
+            while !state.closed && state.queue.len() >= inner.capacity {
+                state = inner.changed.wait(state).unwrap();
+            }
+        }
+        if state.closed {
+            return Err("Cannot send on a closed channel".to_string());
+        }
+
+        state.sent += 1;
+        let ticket = state.sent;
+        state.queue.push_back((ticket, value));
+        inner.changed.notify_all();
+        drop(state);
+        notify_activity();
+
+        if inner.capacity == 0 {
+            let mut state = inner.state.lock().unwrap();
+            while !state.closed && state.received < ticket {
+                state = inner.changed.wait(state).unwrap();
+            }
+            // Closed before anyone took the value, which is withdrawn so
+            // that it is not received after the send failed.
+            if let Some(index) = state.queue.iter().position(|(queued, _)| *queued == ticket) {
+                state.queue.remove(index);
+                return Err("Cannot send on a closed channel".to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Blocks until a value is available. Receiving from a closed, drained
+    /// channel yields `nil`.
+    pub fn receive(&self) -> Value {
+        let inner = &self.inner;
+        let mut state = inner.state.lock().unwrap();
+
+        state.waiting_receivers += 1;
+        while state.queue.is_empty() && !state.closed {
+            state = inner.changed.wait(state).unwrap();
+        }
+        state.waiting_receivers -= 1;
+
+        let value = Self::take(&mut state);
+        inner.changed.notify_all();
+        value.unwrap_or(Value::Nil)
+    }
+
+    /// Sends without blocking. On an unbuffered channel this only succeeds
+    /// when a receiver is blocked in `receive`. A waiting `select` does not
+    /// count, as it may take another of its channels and leave the value
+    /// with no receiver.
+    pub fn try_send(&self, value: Value) -> Result<bool, String> {
+        let inner = &self.inner;
+        let mut state = inner.state.lock().unwrap();
+
+        if state.closed {
+            return Err("Cannot send on a closed channel".to_string());
+        }
+        let has_room = if inner.capacity > 0 {
+            state.queue.len() < inner.capacity
+        } else {
+            state.waiting_receivers > state.queue.len()
+        };
+        if !has_room {
+            return Ok(false);
+        }
+
+        state.sent += 1;
+        let ticket = state.sent;
+        state.queue.push_back((ticket, value));
+        inner.changed.notify_all();
+        drop(state);
+        notify_activity();
+
+        Ok(true)
+    }
+
+    pub fn try_receive(&self) -> Option<Value> {
+        let mut state = self.inner.state.lock().unwrap();
+        let value = Self::take(&mut state);
+        if value.is_some() {
+            self.inner.changed.notify_all();
+        }
+        value
+    }
+
+    pub fn close(&self) {
+        let mut state = self.inner.state.lock().unwrap();
+        state.closed = true;
+        self.inner.changed.notify_all();
+        drop(state);
+        notify_activity();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.state.lock().unwrap().closed
+    }
+
+    fn take(state: &mut ChannelState) -> Option<Value> {
+        let value = state.queue.pop_front();
+        if value.is_some() {
+            state.received += 1;
+        }
+        value.map(|(_, value)| value)
+    }
+
+    // A closed channel is always ready: receiving from it yields `nil`.
+    fn poll(&self) -> Option<Value> {
+        let mut state = self.inner.state.lock().unwrap();
+        if state.queue.is_empty() && !state.closed {
+            return None;
+        }
+
+        let value = Self::take(&mut state);
+        self.inner.changed.notify_all();
+        Some(value.unwrap_or(Value::Nil))
+    }
+}
+
+pub enum Selected {
+    Received(usize, Value),
+    TimedOut,
+}
+
+/// Waits until one of `channels` has a value or `timeout` elapses. When
+/// several channels are ready at once one of them is picked at random, so
+/// no channel can starve the others.
+pub fn select(channels: &[Channel], timeout: Option<Duration>) -> Selected {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut generation = ACTIVITY.lock().unwrap();
+
+    loop {
+        let start = random_index(channels.len());
+        for offset in 0..channels.len() {
+            let index = (start + offset) % channels.len();
+            if let Some(value) = channels[index].poll() {
+                return Selected::Received(index, value);
+            }
+        }
+
+        let seen = *generation;
+        match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Selected::TimedOut;
+                }
+                generation = ACTIVITY_CHANGED
+                    .wait_timeout_while(generation, deadline - now, |current| *current == seen)
+                    .unwrap()
+                    .0;
+            }
+            None => {
+                generation = ACTIVITY_CHANGED
+                    .wait_while(generation, |current| *current == seen)
+                    .unwrap();
+            }
+        }
+    }
+}
+
+fn random_index(len: usize) -> usize {
+    if len <= 1 {
+        return 0;
+    }
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_usize(len);
+    (hasher.finish() % len as u64) as usize
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncGroup {
+    inner: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl SyncGroup {
+    pub fn new(count: usize) -> Self {
+        SyncGroup {
+            inner: Arc::new((Mutex::new(count), Condvar::new())),
+        }
+    }
+
+    pub fn ptr_eq(&self, other: &SyncGroup) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    pub fn ready(&self) {
+        let (pending, changed) = &*self.inner;
+        let mut pending = pending.lock().unwrap();
+        *pending = pending.saturating_sub(1);
+        changed.notify_all();
+    }
+
+    pub fn wait(&self) {
+        let (pending, changed) = &*self.inner;
+        let pending = pending.lock().unwrap();
+        let _pending = changed.wait_while(pending, |pending| *pending > 0).unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::runtime::parser::parser::Parser;
+
+#[derive(Debug, Default)]
+pub struct FileCoverage {
+    // How often each statement line ran, including the ones that never did.
+    pub lines: BTreeMap<usize, u64>,
+    // The line of each branch point and how often each of its arms ran.
+    pub branches: Vec<(usize, Vec<u64>)>,
+}
+
+/// Which statements and branches ran, for every file registered before it
+/// ran. Files are keyed by their canonical path, as imports are.
+#[derive(Debug, Default)]
+pub struct Coverage {
+    pub files: BTreeMap<PathBuf, FileCoverage>,
+}
+
+impl FileCoverage {
+    fn lines_hit(&self) -> usize {
+        self.lines.values().filter(|hits| **hits > 0).count()
+    }
+
+    fn arms(&self) -> usize {
+        self.branches.iter().map(|(_, arms)| arms.len()).sum()
+    }
+
+    fn arms_hit(&self) -> usize {
+        let arms = self.branches.iter().flat_map(|(_, arms)| arms);
+        arms.filter(|taken| **taken > 0).count()
+    }
+}
+
+impl Coverage {
+    /// Starts tracking a file with what `parser` found in it. A parser
+    /// built `with_lines` is needed to know where things are.
+    pub fn register(&mut self, path: &Path, parser: &Parser) {
+        self.files
+            .entry(path.to_path_buf())
+            .or_insert_with(|| FileCoverage {
+                lines: parser
+                    .statement_lines()
+                    .iter()
+                    .map(|line| (*line, 0))
+                    .collect(),
+                branches: parser
+                    .branch_points()
+                    .iter()
+                    .map(|point| (point.line, vec![0; point.arms]))
+                    .collect(),
+            });
+    }
+
+    pub fn hit_line(&mut self, path: &Path, line: usize) {
+        if let Some(file) = self.files.get_mut(path) {
+            *file.lines.entry(line).or_default() += 1;
+        }
+    }
+
+    pub fn take_branch(&mut self, path: &Path, branch: usize, arm: usize) {
+        let file = self.files.get_mut(path);
+        if let Some((_, arms)) = file.and_then(|file| file.branches.get_mut(branch)) {
+            if let Some(taken) = arms.get_mut(arm) {
+                *taken += 1;
+            }
+        }
+    }
+
+    /// The coverage as an LCOV tracefile.
+    pub fn lcov(&self) -> String {
+        let mut lcov = String::new();
+        for (path, file) in &self.files {
+            writeln!(lcov, "TN:\nSF:{}", path.display()).unwrap();
+            for (block, (line, arms)) in file.branches.iter().enumerate() {
+                let reached = arms.iter().any(|taken| *taken > 0);
+                for (arm, taken) in arms.iter().enumerate() {
+                    // `-` marks a branch point that never ran at all.
+                    let taken = if reached {
+                        taken.to_string()
+                    } else {
+                        "-".to_string()
+                    };
+                    writeln!(lcov, "BRDA:{},{},{},{}", line, block, arm, taken).unwrap();
+                }
+            }
+            writeln!(lcov, "BRF:{}\nBRH:{}", file.arms(), file.arms_hit()).unwrap();
+            for (line, hits) in &file.lines {
+                writeln!(lcov, "DA:{},{}", line, hits).unwrap();
+            }
+            writeln!(lcov, "LF:{}\nLH:{}", file.lines.len(), file.lines_hit()).unwrap();
+            lcov.push_str("end_of_record\n");
+        }
+        lcov
+    }
+
+    /// A table of the lines and branches covered in each file, with paths
+    /// shown relative to `base` when they are inside it.
+    pub fn summary(&self, base: &Path) -> String {
+        let mut summary = String::new();
+        writeln!(summary, "{:<40} {:>16} {:>16}", "file", "lines", "branches").unwrap();
+
+        let (mut lines, mut lines_hit, mut arms, mut arms_hit) = (0, 0, 0, 0);
+        for (path, file) in &self.files {
+            let name = path.strip_prefix(base).unwrap_or(path).display();
+            writeln!(
+                summary,
+                "{:<40} {:>16} {:>16}",
+                name.to_string(),
+                ratio(file.lines_hit(), file.lines.len()),
+                ratio(file.arms_hit(), file.arms())
+            )
+            .unwrap();
+            lines += file.lines.len();
+            lines_hit += file.lines_hit();
+            arms += file.arms();
+            arms_hit += file.arms_hit();
+        }
+
+        writeln!(
+            summary,
+            "{:<40} {:>16} {:>16}",
+            "total",
+            ratio(lines_hit, lines),
+            ratio(arms_hit, arms)
+        )
+        .unwrap();
+        summary
+    }
+}
+
+fn ratio(hit: usize, total: usize) -> String {
+    if total == 0 {
+        return "-".to_string();
+    }
+    let percent = hit as f64 * 100.0 / total as f64;
+    format!("{}/{} {:5.1}%", hit, total, percent)
+}
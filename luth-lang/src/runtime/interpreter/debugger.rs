@@ -0,0 +1,267 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, Sender},
+        Arc, Mutex,
+    },
+};
+
+use crate::runtime::interpreter::environment::{Environment, Value};
+
+/// Breakpoint lines in one file, each with an optional condition that must
+/// evaluate to a truthy value for execution to stop.
+pub type FileBreakpoints = HashMap<usize, Option<String>>;
+
+/// The breakpoints of the program being debugged, by canonical file path.
+pub type Breakpoints = Arc<Mutex<HashMap<PathBuf, FileBreakpoints>>>;
+
+/// What a paused program reports to the debugger front end.
+#[derive(Debug)]
+pub enum Event {
+    Stopped {
+        reason: &'static str,
+        line: usize,
+    },
+    Output {
+        category: &'static str,
+        text: String,
+    },
+    Exited {
+        status: i32,
+    },
+}
+
+/// What the front end asks of a paused program.
+pub enum Request {
+    Resume(Resume),
+    StackTrace(Sender<Vec<(String, Option<PathBuf>, usize)>>),
+    Variables {
+        frame: usize,
+        scope: Scope,
+        reply: Sender<Vec<Variable>>,
+    },
+    Evaluate {
+        frame: usize,
+        expression: String,
+        reply: Sender<Result<Variable, String>>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resume {
+    Continue,
+    Next,
+    StepIn,
+    StepOut,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scope {
+    Locals,
+    Globals,
+}
+
+#[derive(Debug, Clone)]
+pub struct Variable {
+    pub name: String,
+    pub value: String,
+    pub type_name: String,
+}
+
+impl Variable {
+    pub fn new(name: String, value: &Value) -> Self {
+        Variable {
+            name,
+            value: value.to_string(),
+            type_name: value.type_name(),
+        }
+    }
+}
+
+// When to stop next regardless of breakpoints. The depths are the number
+// of frames when the step was requested.
+enum Step {
+    Run,
+    In,
+    Over(usize),
+    Out(usize),
+}
+
+pub struct Frame {
+    pub name: String,
+    // The file of the statement the frame is at, if the program has one.
+    pub file: Option<PathBuf>,
+    pub line: usize,
+    pub environment: Arc<Mutex<Environment>>,
+}
+
+/// The interpreter's side of a debugging session: the call stack and the
+/// channels to the front end, which runs on another thread.
+pub struct Debugger {
+    breakpoints: Breakpoints,
+    pause: Arc<AtomicBool>,
+    requests: Receiver<Request>,
+    events: Sender<Event>,
+    step: Step,
+    frames: Vec<Frame>,
+}
+
+impl Debugger {
+    pub fn new(requests: Receiver<Request>, events: Sender<Event>, stop_on_entry: bool) -> Self {
+        Debugger {
+            breakpoints: Breakpoints::default(),
+            pause: Arc::new(AtomicBool::new(false)),
+            requests,
+            events,
+            step: if stop_on_entry { Step::In } else { Step::Run },
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn breakpoints(&self) -> Breakpoints {
+        Arc::clone(&self.breakpoints)
+    }
+
+    /// A flag the front end sets to stop before the next statement.
+    pub fn pause_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.pause)
+    }
+
+    pub fn events(&self) -> Sender<Event> {
+        self.events.clone()
+    }
+
+    pub fn push_frame(&mut self, name: String, environment: Arc<Mutex<Environment>>) {
+        self.frames.push(Frame {
+            name,
+            file: None,
+            line: 0,
+            environment,
+        });
+    }
+
+    pub fn pop_frame(&mut self) {
+        self.frames.pop();
+    }
+
+    pub fn frame(&self, index: usize) -> Option<&Frame> {
+        self.frames.iter().rev().nth(index)
+    }
+
+    /// Moves the innermost frame to a statement on `line` of `file` and
+    /// returns why execution should stop there, if it should. A breakpoint
+    /// condition is returned for the caller to evaluate.
+    pub fn enter(
+        &mut self,
+        file: Option<&Path>,
+        line: usize,
+        environment: &Arc<Mutex<Environment>>,
+    ) -> Option<(&'static str, Option<String>)> {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.file = file.map(Path::to_path_buf);
+            frame.line = line;
+            frame.environment = Arc::clone(environment);
+        }
+
+        let depth = self.frames.len();
+        let stepped = match self.step {
+            Step::Run => false,
+            Step::In => true,
+            Step::Over(from) => depth <= from,
+            Step::Out(from) => depth < from,
+        };
+
+        if self.pause.swap(false, Ordering::SeqCst) {
+            Some(("pause", None))
+        } else if stepped {
+            Some(("step", None))
+        } else {
+            let breakpoints = self.breakpoints.lock().unwrap();
+            let condition = breakpoints.get(file?)?.get(&line)?;
+            Some(("breakpoint", condition.clone()))
+        }
+    }
+
+    pub fn stopped(&self, reason: &'static str, line: usize) {
+        let _ = self.events.send(Event::Stopped { reason, line });
+    }
+
+    /// The next request that needs the interpreter, answering the others
+    /// itself. Returns `None` once the program should run again.
+    pub fn next_request(&mut self) -> Option<Request> {
+        loop {
+            let request = match self.requests.recv() {
+                Ok(request) => request,
+                // The front end went away, so nothing can resume us later.
+                Err(_) => {
+                    self.step = Step::Run;
+                    self.breakpoints.lock().unwrap().clear();
+                    return None;
+                }
+            };
+
+            match request {
+                Request::Resume(resume) => {
+                    let depth = self.frames.len();
+                    self.step = match resume {
+                        Resume::Continue => Step::Run,
+                        Resume::Next => Step::Over(depth),
+                        Resume::StepIn => Step::In,
+                        Resume::StepOut => Step::Out(depth),
+                    };
+                    return None;
+                }
+                Request::StackTrace(reply) => {
+                    let frames = self.frames.iter().rev();
+                    let _ = reply.send(
+                        frames
+                            .map(|frame| (frame.name.clone(), frame.file.clone(), frame.line))
+                            .collect(),
+                    );
+                }
+                Request::Variables {
+                    frame,
+                    scope,
+                    reply,
+                } => {
+                    let _ = reply.send(self.variables(frame, scope));
+                }
+                request => return Some(request),
+            }
+        }
+    }
+
+    // Locals are every scope between the frame and the global one, with
+    // inner declarations hiding outer ones.
+    fn variables(&self, frame: usize, scope: Scope) -> Vec<Variable> {
+        let Some(frame) = self.frame(frame) else {
+            return Vec::new();
+        };
+
+        let mut scopes = Vec::new();
+        let mut environment = Some(Arc::clone(&frame.environment));
+        while let Some(current) = environment {
+            environment = current.lock().unwrap().enclosing();
+            scopes.push(current);
+        }
+        let globals = scopes.pop();
+        if scope == Scope::Globals {
+            scopes = globals.into_iter().collect();
+        }
+
+        let mut seen = HashMap::new();
+        for environment in scopes {
+            for (name, value) in environment.lock().unwrap().variables() {
+                seen.entry(name).or_insert(value);
+            }
+        }
+        let mut variables: Vec<_> = seen
+            .into_iter()
+            .map(|(name, value)| Variable::new(name, &value))
+            .collect();
+        variables.sort_by(|a, b| a.name.cmp(&b.name));
+        variables
+    }
+}
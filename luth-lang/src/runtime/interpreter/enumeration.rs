@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use crate::runtime::interpreter::environment::Value;
+use crate::runtime::parser::ast::Variant;
+
+#[derive(Debug)]
+pub struct Enum {
+    pub name: String,
+    pub variants: Vec<Variant>,
+}
+
+impl Enum {
+    pub fn new(name: String, variants: Vec<Variant>) -> Self {
+        Enum { name, variants }
+    }
+
+    fn variant(&self, name: &str) -> Result<&Variant, String> {
+        self.variants
+            .iter()
+            .find(|variant| variant.name == name)
+            .ok_or_else(|| format!("Enum '{}' has no variant '{}'", self.name, name))
+    }
+}
+
+#[derive(Debug)]
+pub struct EnumValue {
+    pub enumeration: Arc<Enum>,
+    pub variant: String,
+    pub values: Vec<Value>,
+}
+
+impl EnumValue {
+    pub fn new(enumeration: Arc<Enum>, variant: &str, values: Vec<Value>) -> Result<Self, String> {
+        let fields = enumeration.variant(variant)?.fields.len();
+        if values.len() != fields {
+            return Err(format!(
+                "Expected {} values in '{}.{}', found {}",
+                fields,
+                enumeration.name,
+                variant,
+                values.len()
+            ));
+        }
+
+        Ok(EnumValue {
+            variant: variant.to_string(),
+            enumeration,
+            values,
+        })
+    }
+}
@@ -1,14 +1,26 @@
 use std::{
+    cell::RefCell,
     cmp::Ordering,
     collections::HashMap,
     ops::{Add, Div, Mul, Rem, Sub},
+    sync::{Arc, Mutex},
 };
 
+use crate::runtime::interpreter::class::{Class, Object};
+use crate::runtime::interpreter::concurrency::{Channel, SyncGroup};
+use crate::runtime::interpreter::enumeration::{Enum, EnumValue};
+use crate::runtime::interpreter::exception::Exception;
+use crate::runtime::interpreter::function::Function;
+use crate::runtime::interpreter::module::Module;
+use crate::runtime::interpreter::structs::{Instance, Struct};
+use crate::runtime::parser::ast::Binding;
 use crate::runtime::parser::parser::Type;
 
 #[derive(Debug)]
 pub struct Environment {
-    variables: HashMap<String, (Value, Option<Type>)>,
+    // Each variable keeps its declared type and whether it may be reassigned.
+    variables: HashMap<String, (Value, Option<Type>, Binding)>,
+    enclosing: Option<Arc<Mutex<Environment>>>,
 }
 
 #[derive(Debug, Clone)]
@@ -16,97 +28,162 @@ pub enum Value {
     StringLiteral(String),
     NumberLiteral(f64),
     BooleanLiteral(bool),
+    List(Arc<Mutex<Vec<Value>>>),
+    Function(Arc<Function>),
+    Struct(Arc<Struct>),
+    Instance(Arc<Instance>),
+    Class(Arc<Class>),
+    Object(Arc<Object>),
+    Enum(Arc<Enum>),
+    Variant(Arc<EnumValue>),
+    Channel(Channel),
+    Sync(SyncGroup),
+    Module(Arc<Module>),
+    Error(Arc<Exception>),
     Nil,
 }
 
 impl Add for Value {
-    type Output = Value;
+    type Output = Result<Value, String>;
 
     fn add(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
             (Value::NumberLiteral(lhs), Value::NumberLiteral(rhs)) => {
-                Value::NumberLiteral(lhs + rhs)
+                Ok(Value::NumberLiteral(lhs + rhs))
             }
-            _ => Value::Nil,
+            (lhs, rhs) => Err(type_mismatch("+", &lhs, &rhs)),
         }
     }
 }
 
 impl Sub for Value {
-    type Output = Value;
+    type Output = Result<Value, String>;
 
     fn sub(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
             (Value::NumberLiteral(lhs), Value::NumberLiteral(rhs)) => {
-                Value::NumberLiteral(lhs - rhs)
+                Ok(Value::NumberLiteral(lhs - rhs))
             }
-            _ => Value::Nil,
+            (lhs, rhs) => Err(type_mismatch("-", &lhs, &rhs)),
         }
     }
 }
 
 impl Mul for Value {
-    type Output = Value;
+    type Output = Result<Value, String>;
 
     fn mul(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
             (Value::NumberLiteral(lhs), Value::NumberLiteral(rhs)) => {
-                Value::NumberLiteral(lhs * rhs)
+                Ok(Value::NumberLiteral(lhs * rhs))
             }
-            _ => Value::Nil,
+            (lhs, rhs) => Err(type_mismatch("*", &lhs, &rhs)),
         }
     }
 }
 
 impl Div for Value {
-    type Output = Value;
+    type Output = Result<Value, String>;
 
     fn div(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
             (Value::NumberLiteral(lhs), Value::NumberLiteral(rhs)) => {
-                Value::NumberLiteral(lhs / rhs)
+                Ok(Value::NumberLiteral(lhs / rhs))
             }
-            _ => Value::Nil,
+            (lhs, rhs) => Err(type_mismatch("/", &lhs, &rhs)),
         }
     }
 }
 
 impl Rem for Value {
-    type Output = Value;
+    type Output = Result<Value, String>;
 
     fn rem(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
             (Value::NumberLiteral(lhs), Value::NumberLiteral(rhs)) => {
-                Value::NumberLiteral(lhs % rhs)
+                Ok(Value::NumberLiteral(lhs % rhs))
             }
-            _ => Value::Nil,
+            (lhs, rhs) => Err(type_mismatch("%", &lhs, &rhs)),
         }
     }
 }
 
 impl Value {
-    pub fn pow(&self, other: &Value) -> Value {
+    pub fn pow(&self, other: &Value) -> Result<Value, String> {
         match (self, other) {
             (Value::NumberLiteral(base), Value::NumberLiteral(exp)) => {
-                Value::NumberLiteral(base.powf(*exp))
+                Ok(Value::NumberLiteral(base.powf(*exp)))
             }
-            _ => Value::Nil,
+            (lhs, rhs) => Err(type_mismatch("**", lhs, rhs)),
         }
     }
 }
 
+fn type_mismatch(operator: &str, lhs: &Value, rhs: &Value) -> String {
+    format!(
+        "Type mismatch: cannot apply '{}' to '{}' and '{}'",
+        operator,
+        lhs.type_name(),
+        rhs.type_name()
+    )
+}
+
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Value::StringLiteral(s1), Value::StringLiteral(s2)) => s1 == s2,
             (Value::NumberLiteral(n1), Value::NumberLiteral(n2)) => n1 == n2,
             (Value::BooleanLiteral(b1), Value::BooleanLiteral(b2)) => b1 == b2,
+            (Value::List(l1), Value::List(l2)) => lists_equal(l1, l2),
+            (Value::Function(f1), Value::Function(f2)) => Arc::ptr_eq(f1, f2),
+            (Value::Struct(s1), Value::Struct(s2)) => Arc::ptr_eq(s1, s2),
+            (Value::Instance(i1), Value::Instance(i2)) => {
+                Arc::ptr_eq(i1, i2)
+                    || (Arc::ptr_eq(&i1.structure, &i2.structure) && i1.values() == i2.values())
+            }
+            (Value::Class(c1), Value::Class(c2)) => Arc::ptr_eq(c1, c2),
+            (Value::Object(o1), Value::Object(o2)) => Arc::ptr_eq(o1, o2),
+            (Value::Enum(e1), Value::Enum(e2)) => Arc::ptr_eq(e1, e2),
+            (Value::Variant(v1), Value::Variant(v2)) => {
+                Arc::ptr_eq(&v1.enumeration, &v2.enumeration)
+                    && v1.variant == v2.variant
+                    && v1.values == v2.values
+            }
+            (Value::Channel(c1), Value::Channel(c2)) => c1.ptr_eq(c2),
+            (Value::Sync(s1), Value::Sync(s2)) => s1.ptr_eq(s2),
+            (Value::Module(m1), Value::Module(m2)) => Arc::ptr_eq(m1, m2),
+            (Value::Error(e1), Value::Error(e2)) => Arc::ptr_eq(e1, e2),
             (Value::Nil, Value::Nil) => true,
             _ => false,
         }
     }
 }
 
+thread_local! {
+    // The pairs of lists being compared on this thread. Meeting a pair
+    // again means both lists recur in the same way, so that part is equal.
+    static COMPARING_LISTS: RefCell<Vec<(usize, usize)>> = const { RefCell::new(Vec::new()) };
+}
+
+fn lists_equal(l1: &Arc<Mutex<Vec<Value>>>, l2: &Arc<Mutex<Vec<Value>>>) -> bool {
+    if Arc::ptr_eq(l1, l2) {
+        return true;
+    }
+    let pair = (Arc::as_ptr(l1) as usize, Arc::as_ptr(l2) as usize);
+    if COMPARING_LISTS.with(|comparing| comparing.borrow().contains(&pair)) {
+        return true;
+    }
+
+    // Each list is copied under its own lock, so no two locks are ever held
+    // at once and lists may contain themselves.
+    let elements1 = l1.lock().unwrap().clone();
+    let elements2 = l2.lock().unwrap().clone();
+    COMPARING_LISTS.with(|comparing| comparing.borrow_mut().push(pair));
+    let equal = elements1 == elements2;
+    COMPARING_LISTS.with(|comparing| comparing.borrow_mut().pop());
+    equal
+}
+
 impl PartialOrd for Value {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         match (self, other) {
@@ -116,45 +193,134 @@ impl PartialOrd for Value {
     }
 }
 
+impl Value {
+    pub fn matches_type(&self, value_type: &Type) -> bool {
+        match (self, value_type) {
+            (Value::StringLiteral(_), Type::String)
+            | (Value::NumberLiteral(_), Type::Int)
+            | (Value::BooleanLiteral(_), Type::Boolean) => true,
+            (Value::Object(object), Type::Named(name)) => object.class.is_named(name),
+            (value, Type::Named(name)) => value.type_name() == *name,
+            _ => false,
+        }
+    }
+
+    /// The name `type_of` reports: the struct, class or enum a value was
+    /// built from, or the kind of a built-in value.
+    pub fn type_name(&self) -> String {
+        let name = match self {
+            Value::StringLiteral(_) => "String",
+            Value::NumberLiteral(_) => "Int",
+            Value::BooleanLiteral(_) => "Bool",
+            Value::List(_) => "List",
+            Value::Function(_) => "Function",
+            Value::Struct(_) => "Struct",
+            Value::Instance(instance) => &instance.structure.name,
+            Value::Class(_) => "Class",
+            Value::Object(object) => &object.class.name,
+            Value::Enum(_) => "Enum",
+            Value::Variant(variant) => &variant.enumeration.name,
+            Value::Channel(_) => "Channel",
+            Value::Sync(_) => "Sync",
+            Value::Module(_) => "Module",
+            Value::Error(_) => "Error",
+            Value::Nil => "Nil",
+        };
+        name.to_string()
+    }
+
+    pub fn is_instance_of(&self, class: &Value) -> Result<bool, String> {
+        match (self, class) {
+            (Value::Object(object), Value::Class(class)) => Ok(object.class.is_subclass_of(class)),
+            (Value::Instance(instance), Value::Struct(structure)) => {
+                Ok(Arc::ptr_eq(&instance.structure, structure))
+            }
+            (Value::Variant(variant), Value::Enum(enumeration)) => {
+                Ok(Arc::ptr_eq(&variant.enumeration, enumeration))
+            }
+            (_, Value::Class(_) | Value::Struct(_) | Value::Enum(_)) => Ok(false),
+            (_, class) => Err(format!(
+                "Right side of 'instanceof' must be a class, found '{}'",
+                class
+            )),
+        }
+    }
+}
+
 impl Environment {
     pub fn new() -> Self {
         Environment {
             variables: HashMap::new(),
+            enclosing: None,
+        }
+    }
+
+    pub fn with_enclosing(enclosing: Arc<Mutex<Environment>>) -> Self {
+        Environment {
+            variables: HashMap::new(),
+            enclosing: Some(enclosing),
         }
     }
 
     pub fn define(&mut self, name: String, value: Value, value_type: Option<Type>) {
-        self.variables.insert(name, (value, value_type));
+        self.declare(name, value, value_type, Binding::Mutable);
+    }
+
+    pub fn declare(
+        &mut self,
+        name: String,
+        value: Value,
+        value_type: Option<Type>,
+        binding: Binding,
+    ) {
+        self.variables.insert(name, (value, value_type, binding));
     }
 
     pub fn assign(&mut self, name: String, value: Value) -> Result<(), String> {
-        if let Some((_, Some(expected_type))) = self.variables.get(&name) {
-            match (&value, expected_type) {
-                (Value::StringLiteral(_), Type::String) => {
-                    self.variables.insert(name, (value, Some(Type::String)));
-                    Ok(())
+        if let Some((current, value_type, binding)) = self.variables.get_mut(&name) {
+            match binding {
+                Binding::Constant => {
+                    return Err(format!("Cannot assign to constant '{}'", name));
                 }
-                (Value::NumberLiteral(_), Type::Int) => {
-                    self.variables.insert(name, (value, Some(Type::Int)));
-                    Ok(())
+                Binding::Immutable => {
+                    return Err(format!("Cannot assign to immutable variable '{}'", name));
                 }
-                (Value::BooleanLiteral(_), Type::Boolean) => {
-                    self.variables.insert(name, (value, Some(Type::Boolean)));
-                    Ok(())
+                Binding::Mutable => {}
+            }
+            if let Some(expected_type) = value_type {
+                if !value.matches_type(expected_type) {
+                    return Err(format!("Type mismatch for variable '{}'", name));
                 }
-                _ => Err(format!("Type mismatch for variable '{}'", name)),
             }
-        } else if let Some((_, None)) = self.variables.get(&name) {
-            self.variables.insert(name, (value, None));
+            *current = value;
             Ok(())
+        } else if let Some(enclosing) = &self.enclosing {
+            enclosing.lock().unwrap().assign(name, value)
         } else {
             Err(format!("Variable '{}' not declared", name))
         }
     }
 
+    /// The variables declared directly in this scope, sorted by name.
+    pub fn variables(&self) -> Vec<(String, Value)> {
+        let mut variables: Vec<_> = self
+            .variables
+            .iter()
+            .map(|(name, (value, _, _))| (name.clone(), value.clone()))
+            .collect();
+        variables.sort_by(|(a, _), (b, _)| a.cmp(b));
+        variables
+    }
+
+    pub fn enclosing(&self) -> Option<Arc<Mutex<Environment>>> {
+        self.enclosing.clone()
+    }
+
     pub fn get(&self, name: &str) -> Result<Value, String> {
-        if let Some((value, _)) = self.variables.get(name) {
+        if let Some((value, _, _)) = self.variables.get(name) {
             Ok(value.clone())
+        } else if let Some(enclosing) = &self.enclosing {
+            enclosing.lock().unwrap().get(name)
         } else {
             Err(format!("Undefined variable '{}'", name))
         }
@@ -0,0 +1,49 @@
+use std::sync::{Arc, Mutex};
+
+use crate::runtime::interpreter::environment::Value;
+
+/// An error on its way up to the nearest `catch`. Both `throw` and failing
+/// built-in operations produce one; the trace collects the functions it
+/// unwinds through, innermost first.
+#[derive(Debug, Clone)]
+pub struct Exception {
+    pub value: Value,
+    pub message: String,
+    pub trace: Vec<String>,
+}
+
+impl Exception {
+    pub fn new(value: Value) -> Self {
+        match value {
+            // Rethrowing a caught error keeps its original trace.
+            Value::Error(exception) => Exception::clone(&exception),
+            value => Exception {
+                message: value.to_string(),
+                value,
+                trace: Vec::new(),
+            },
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        match name {
+            "message" => Some(Value::StringLiteral(self.message.clone())),
+            "value" => Some(self.value.clone()),
+            "stack" => {
+                let frames = self.trace.iter().cloned().map(Value::StringLiteral);
+                Some(Value::List(Arc::new(Mutex::new(frames.collect()))))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Exception {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        for frame in &self.trace {
+            write!(f, "\n    at {}", frame)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::runtime::interpreter::environment::{Environment, Value};
+use crate::runtime::parser::ast::FunctionDeclaration;
+
+/// A function value together with the scope it was created in. Captured
+/// variables live in `closure`, so they outlive the block that declared them
+/// and stay shared between the function and that block.
+pub struct Function {
+    pub declaration: Arc<FunctionDeclaration>,
+    pub closure: Arc<Mutex<Environment>>,
+    // The file the function was declared in, if it came from one.
+    pub file: Option<PathBuf>,
+    // The struct or class a method belongs to.
+    pub owner: Option<String>,
+}
+
+impl Function {
+    pub fn new(
+        declaration: FunctionDeclaration,
+        closure: Arc<Mutex<Environment>>,
+        file: Option<PathBuf>,
+    ) -> Self {
+        Function {
+            declaration: Arc::new(declaration),
+            closure,
+            file,
+            owner: None,
+        }
+    }
+
+    /// Marks this function as a method of the struct or class `owner`.
+    pub fn method_of(mut self, owner: &str) -> Self {
+        self.owner = Some(owner.to_string());
+        self
+    }
+
+    /// Returns a copy of this method whose scope defines `this`.
+    pub fn bind(&self, this: Value) -> Function {
+        let mut environment = Environment::with_enclosing(Arc::clone(&self.closure));
+        environment.define("this".to_string(), this, None);
+
+        Function {
+            declaration: Arc::clone(&self.declaration),
+            closure: Arc::new(Mutex::new(environment)),
+            file: self.file.clone(),
+            owner: self.owner.clone(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.declaration.name.as_deref().unwrap_or("anonymous")
+    }
+
+    /// The name with the type it is a method of, as in `Point.length`.
+    pub fn qualified_name(&self) -> String {
+        match &self.owner {
+            Some(owner) => format!("{}.{}", owner, self.name()),
+            None => self.name().to_string(),
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.declaration.parameters.len()
+    }
+
+    /// Methods whose first parameter is `self` receive the instance they
+    /// are called on.
+    pub fn takes_self(&self) -> bool {
+        self.declaration
+            .parameters
+            .first()
+            .is_some_and(|parameter| parameter.name == "self")
+    }
+}
+
+// The closure usually contains the function itself, so it is left out.
+impl std::fmt::Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Function({})", self.name())
+    }
+}
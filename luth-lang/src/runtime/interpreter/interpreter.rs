@@ -1,99 +1,687 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{mpsc::Sender, Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::runtime::interpreter::builtins;
+use crate::runtime::interpreter::class::{Class, Object};
+use crate::runtime::interpreter::concurrency::{self, Selected};
+use crate::runtime::interpreter::coverage::Coverage;
+use crate::runtime::interpreter::debugger::{Debugger, Event, Request, Variable};
+use crate::runtime::interpreter::enumeration::{Enum, EnumValue};
 use crate::runtime::interpreter::environment::{Environment, Value};
-use crate::runtime::parser::ast::{Expression, Operator, Statement};
+use crate::runtime::interpreter::exception::Exception;
+use crate::runtime::interpreter::function::Function;
+use crate::runtime::interpreter::module::{self, Module, ModuleLoader};
+use crate::runtime::interpreter::pattern;
+use crate::runtime::interpreter::profiler::{FunctionId, Profiler};
+use crate::runtime::interpreter::result;
+use crate::runtime::interpreter::structs::{Instance, Struct};
+use crate::runtime::lexer::lexer::{lexer, lexer_with_lines};
+use crate::runtime::parser::ast::{
+    CatchClause, Expression, MatchArm, Operator, SelectArm, Statement,
+};
+use crate::runtime::parser::parser::Parser;
+
+/// Unwinds `execute` up to the construct that handles it: a `return` up to
+/// the enclosing function call, an error up to the nearest `catch` or
+/// `interpret`, and `exit()` all the way up to `interpret`.
+pub enum Interrupt {
+    Return(Value),
+    Error(Box<Exception>),
+    Exit(i32),
+}
+
+impl From<String> for Interrupt {
+    fn from(message: String) -> Self {
+        Interrupt::Error(Box::new(Exception::new(Value::StringLiteral(message))))
+    }
+}
 
 pub struct Interpreter {
-    environment: Environment,
+    environment: Arc<Mutex<Environment>>,
+    threads: Vec<JoinHandle<()>>,
+    modules: Arc<ModuleLoader>,
+    // Files whose top level is currently running, innermost last.
+    importing: Vec<PathBuf>,
+    // The file of the code running now, which changes inside calls to
+    // functions declared in other files.
+    file: Option<PathBuf>,
+    // Only the main interpreter of a `luth debug` session has a debugger;
+    // threads and modules run without stopping.
+    debugger: Option<Debugger>,
+    // Where printed text goes instead of stdout, when debugging.
+    output: Option<Sender<Event>>,
+    // Like the debugger, only the main interpreter is profiled.
+    profiler: Option<Profiler>,
+    // Shared with threads and modules, which record into their own file.
+    coverage: Option<Arc<Mutex<Coverage>>>,
 }
 
 impl Interpreter {
     pub fn new() -> Self {
         Interpreter {
-            environment: Environment::new(),
+            environment: Arc::new(Mutex::new(Environment::new())),
+            threads: Vec::new(),
+            modules: Arc::new(ModuleLoader::from_env()),
+            importing: Vec::new(),
+            file: None,
+            debugger: None,
+            output: None,
+            profiler: None,
+            coverage: None,
+        }
+    }
+
+    /// Runs under `debugger`, which sees the top level as frame `main`.
+    pub fn set_debugger(&mut self, mut debugger: Debugger) {
+        debugger.push_frame("main".to_string(), Arc::clone(&self.environment));
+        self.output = Some(debugger.events());
+        self.debugger = Some(debugger);
+    }
+
+    /// Sets the file being run, which imports are resolved against.
+    pub fn set_script_path(&mut self, path: &Path) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.file = Some(path.clone());
+        self.importing = vec![path];
+    }
+
+    /// Defines the program's `args` list.
+    pub fn set_args(&mut self, args: &[String]) {
+        let args = args.iter().cloned().map(Value::StringLiteral).collect();
+        self.environment.lock().unwrap().define(
+            "args".to_string(),
+            Value::List(Arc::new(Mutex::new(args))),
+            None,
+        );
+    }
+
+    pub fn set_profiler(&mut self, profiler: Profiler) {
+        self.profiler = Some(profiler);
+    }
+
+    /// The timings collected so far, ending the profile.
+    pub fn take_profiler(&mut self) -> Option<Profiler> {
+        let mut profiler = self.profiler.take()?;
+        profiler.finish();
+        Some(profiler)
+    }
+
+    /// Records what runs into `coverage`, for the files registered in it.
+    pub fn set_coverage(&mut self, coverage: Arc<Mutex<Coverage>>) {
+        self.coverage = Some(coverage);
+    }
+
+    fn cover_line(&self, line: usize) {
+        if let (Some(coverage), Some(path)) = (&self.coverage, &self.file) {
+            coverage.lock().unwrap().hit_line(path, line);
+        }
+    }
+
+    fn cover_branch(&self, branch: usize, arm: usize) {
+        if let (Some(coverage), Some(path)) = (&self.coverage, &self.file) {
+            coverage.lock().unwrap().take_branch(path, branch, arm);
+        }
+    }
+
+    /// Sends printed text to `output` instead of stdout.
+    pub fn set_output(&mut self, output: Sender<Event>) {
+        self.output = Some(output);
+    }
+
+    /// Runs a program's top level and then the body of one of its tests,
+    /// waiting for any threads they start.
+    pub fn run_test(
+        &mut self,
+        statements: Vec<Statement>,
+        body: Vec<Statement>,
+    ) -> Result<(), Box<Exception>> {
+        let result = self
+            .run(statements)
+            .and_then(|()| self.run(vec![Statement::Block(body)]));
+        self.join_threads();
+        match result {
+            Ok(()) | Err(Interrupt::Return(_)) => Ok(()),
+            Err(Interrupt::Error(exception)) => Err(exception),
+            Err(Interrupt::Exit(status)) => {
+                let message = format!("Exited with status {}", status);
+                Err(Box::new(Exception::new(Value::StringLiteral(message))))
+            }
         }
     }
 
-    pub fn interpret(&mut self, statements: Vec<Statement>) {
+    /// Runs a program, reporting an uncaught error, and returns the status
+    /// it exits with: the one given to `exit()`, 1 after an error, or 0.
+    /// Threads are waited for unless the program called `exit()`.
+    pub fn interpret(&mut self, statements: Vec<Statement>) -> i32 {
+        let status = match self.run(statements) {
+            Ok(()) | Err(Interrupt::Return(_)) => 0,
+            Err(Interrupt::Error(error)) => {
+                self.write("stderr", error.to_string());
+                1
+            }
+            Err(Interrupt::Exit(status)) => return status,
+        };
+        self.join_threads();
+        status
+    }
+
+    fn run(&mut self, statements: Vec<Statement>) -> Result<(), Interrupt> {
         for statement in statements {
             match self.execute(statement) {
                 Ok(()) => {}
-                Err(error) => {
-                    eprintln!("{}", error);
-                    return;
+                Err(Interrupt::Return(_)) => break,
+                Err(interrupt) => return Err(interrupt),
+            }
+        }
+        Ok(())
+    }
+
+    // An interpreter running in `environment` that shares the loaded modules.
+    fn child(&self, environment: Arc<Mutex<Environment>>) -> Interpreter {
+        Interpreter {
+            environment,
+            threads: Vec::new(),
+            modules: Arc::clone(&self.modules),
+            importing: self.importing.clone(),
+            file: self.file.clone(),
+            debugger: None,
+            output: self.output.clone(),
+            profiler: None,
+            coverage: self.coverage.clone(),
+        }
+    }
+
+    fn write(&self, category: &'static str, text: String) {
+        match (&self.output, category) {
+            (Some(output), _) => {
+                let text = text + "\n";
+                let _ = output.send(Event::Output { category, text });
+            }
+            (None, "stderr") => eprintln!("{}", text),
+            (None, _) => println!("{}", text),
+        }
+    }
+
+    // Stops before a statement on `line` if the debugger asks to, serving
+    // its requests until it resumes.
+    fn debug(&mut self, line: usize) {
+        let Some(mut debugger) = self.debugger.take() else {
+            return;
+        };
+
+        let stop = match debugger.enter(self.file.as_deref(), line, &self.environment) {
+            Some((reason, None)) => Some(reason),
+            Some((reason, Some(condition))) => {
+                let environment = Arc::clone(&self.environment);
+                let value = self.evaluate_source(&condition, environment);
+                // A condition that fails to evaluate stops, so the mistake
+                // is noticed.
+                value
+                    .map_or(true, |value| value.is_truthy())
+                    .then_some(reason)
+            }
+            None => None,
+        };
+
+        if let Some(reason) = stop {
+            debugger.stopped(reason, line);
+            while let Some(request) = debugger.next_request() {
+                if let Request::Evaluate {
+                    frame,
+                    expression,
+                    reply,
+                } = request
+                {
+                    let result = match debugger.frame(frame) {
+                        Some(frame) => {
+                            let environment = Arc::clone(&frame.environment);
+                            self.evaluate_source(&expression, environment)
+                                .map(|value| Variable::new(expression, &value))
+                        }
+                        None => Err(format!("Unknown frame {}", frame)),
+                    };
+                    let _ = reply.send(result);
+                }
+            }
+        }
+        self.debugger = Some(debugger);
+    }
+
+    // Evaluates an expression typed by the user in `environment`.
+    fn evaluate_source(
+        &mut self,
+        source: &str,
+        environment: Arc<Mutex<Environment>>,
+    ) -> Result<Value, String> {
+        let source = source.trim().trim_end_matches(';');
+        let statements = Parser::new(lexer(&format!("{};", source))).parse()?;
+        let mut statements = statements.into_iter();
+        let expression = match (statements.next(), statements.next()) {
+            (Some(Statement::Located { statement, .. }), None) => match *statement {
+                Statement::Expression(expression) => expression,
+                _ => return Err(format!("'{}' is not an expression", source)),
+            },
+            _ => return Err(format!("'{}' is not an expression", source)),
+        };
+
+        let previous = std::mem::replace(&mut self.environment, environment);
+        let result = self.evaluate(expression);
+        self.environment = previous;
+        match result {
+            Ok(value) | Err(Interrupt::Return(value)) => Ok(value),
+            Err(Interrupt::Error(exception)) => Err(exception.message),
+            Err(Interrupt::Exit(status)) => Err(format!("Exited with status {}", status)),
+        }
+    }
+
+    fn join_threads(&mut self) {
+        for handle in self.threads.drain(..) {
+            let _ = handle.join();
+        }
+    }
+
+    fn spawn_thread(&mut self, body: Statement) {
+        let mut interpreter = self.child(Arc::clone(&self.environment));
+        let handle = thread::spawn(move || {
+            interpreter.interpret(vec![body]);
+        });
+        self.threads.push(handle);
+    }
+
+    fn select(&mut self, arms: Vec<SelectArm>) -> Result<(), Interrupt> {
+        let mut channels = Vec::new();
+        let mut receivers = Vec::new();
+        let mut timeout = None;
+
+        for arm in arms {
+            match arm {
+                SelectArm::Receive {
+                    channel,
+                    binding,
+                    body,
+                } => match self.evaluate(channel)? {
+                    Value::Channel(channel) => {
+                        channels.push(channel);
+                        receivers.push((binding, body));
+                    }
+                    value => {
+                        return Err(format!("Cannot receive from non-channel '{}'", value).into())
+                    }
+                },
+                SelectArm::Timeout { duration, body } => match self.evaluate(duration)? {
+                    Value::NumberLiteral(ms) if ms >= 0.0 => {
+                        timeout = Some((Duration::from_secs_f64(ms / 1000.0), body));
+                    }
+                    value => return Err(format!("Invalid select timeout '{}'", value).into()),
+                },
+            }
+        }
+
+        let duration = timeout.as_ref().map(|(duration, _)| *duration);
+        match concurrency::select(&channels, duration) {
+            Selected::Received(index, value) => {
+                let (binding, body) = receivers.swap_remove(index);
+                let mut environment = Environment::with_enclosing(Arc::clone(&self.environment));
+                if let Some(name) = binding {
+                    environment.define(name, value, None);
                 }
+                self.execute_block(vec![body], Arc::new(Mutex::new(environment)))
+            }
+            Selected::TimedOut => match timeout {
+                Some((_, body)) => self.execute(body),
+                None => Ok(()),
+            },
+        }
+    }
+
+    fn import(&mut self, path: &str) -> Result<Arc<Module>, Interrupt> {
+        let path = self
+            .modules
+            .resolve(path, self.importing.last().map(PathBuf::as_path))?;
+        if let Some(module) = self.modules.cached(&path) {
+            return Ok(module);
+        }
+
+        if let Some(start) = self.importing.iter().position(|file| *file == path) {
+            let cycle = self.importing[start..]
+                .iter()
+                .chain([&path])
+                .map(|file| file.file_name().unwrap_or_default().to_string_lossy())
+                .collect::<Vec<_>>();
+            return Err(format!("Cyclic import: {}", cycle.join(" -> ")).into());
+        }
+
+        let source = fs::read_to_string(&path)
+            .map_err(|error| format!("Cannot read module '{}': {}", path.display(), error))?;
+        let (tokens, lines) = lexer_with_lines(&source);
+        let mut parser = Parser::with_lines(tokens, lines);
+        let statements = parser
+            .parse()
+            .map_err(|error| format!("{}: {}", path.display(), error))?;
+        if let Some(coverage) = &self.coverage {
+            coverage.lock().unwrap().register(&path, &parser);
+        }
+        for warning in parser.warnings() {
+            eprintln!("Warning: {}: {}", path.display(), warning);
+        }
+
+        let exports = module::exported_names(&statements);
+        let environment = Arc::new(Mutex::new(Environment::new()));
+        let mut interpreter = self.child(Arc::clone(&environment));
+        interpreter.importing.push(path.clone());
+        interpreter.file = Some(path.clone());
+        let result = interpreter.run(statements);
+        interpreter.join_threads();
+        result.map_err(|interrupt| match interrupt {
+            Interrupt::Error(mut exception) => {
+                exception.trace.push(format!("module {}", path.display()));
+                Interrupt::Error(exception)
+            }
+            interrupt => interrupt,
+        })?;
+
+        let module = Arc::new(Module::new(path, environment, exports));
+        self.modules.insert(Arc::clone(&module));
+        Ok(module)
+    }
+
+    fn try_statement(
+        &mut self,
+        body: Vec<Statement>,
+        catch: Option<CatchClause>,
+        finally: Option<Vec<Statement>>,
+    ) -> Result<(), Interrupt> {
+        let mut result = self.execute_scoped(body);
+
+        if let (Err(Interrupt::Error(exception)), Some(catch)) = (&result, catch) {
+            let mut environment = Environment::with_enclosing(Arc::clone(&self.environment));
+            if let Some(name) = catch.name {
+                environment.define(name, Value::Error(Arc::new(*exception.clone())), None);
             }
+            result = self.execute_block(catch.body, Arc::new(Mutex::new(environment)));
         }
+
+        // A `finally` that fails or returns replaces the outcome of the rest.
+        if let Some(finally) = finally {
+            self.execute_scoped(finally)?;
+        }
+        result
+    }
+
+    fn execute_scoped(&mut self, statements: Vec<Statement>) -> Result<(), Interrupt> {
+        let environment = Environment::with_enclosing(Arc::clone(&self.environment));
+        self.execute_block(statements, Arc::new(Mutex::new(environment)))
     }
 
-    fn execute(&mut self, statement: Statement) -> Result<(), String> {
+    fn execute_block(
+        &mut self,
+        statements: Vec<Statement>,
+        environment: Arc<Mutex<Environment>>,
+    ) -> Result<(), Interrupt> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+        let result = statements
+            .into_iter()
+            .try_for_each(|statement| self.execute(statement));
+        self.environment = previous;
+        result
+    }
+
+    fn execute(&mut self, statement: Statement) -> Result<(), Interrupt> {
         match statement {
             Statement::VariableDeclaration {
                 name,
                 value,
                 value_type,
+                binding,
             } => {
-                let val = self.evaluate(value);
-                self.environment.define(name, val, value_type);
+                let val = self.evaluate(value)?;
+                self.environment
+                    .lock()
+                    .unwrap()
+                    .declare(name, val, value_type, binding);
             }
-            Statement::VariableAssignment {
+            Statement::VariableAssignment { name, value } => {
+                let val = self.evaluate(value)?;
+                self.environment.lock().unwrap().assign(name, val)?;
+            }
+            Statement::PropertyAssignment {
+                object,
                 name,
                 value,
-                value_type: _,
             } => {
-                let val = self.evaluate(value);
-                self.environment.assign(name, val)?;
+                let object = self.evaluate(object)?;
+                let value = self.evaluate(value)?;
+                match object {
+                    Value::Instance(instance) => instance.set(&name, value)?,
+                    Value::Object(object) => object.set(name, value),
+                    object => {
+                        return Err(format!("Cannot set property '{}' on '{}'", name, object).into())
+                    }
+                }
+            }
+            Statement::Expression(value) => {
+                self.evaluate(value)?;
             }
             Statement::Print(value) => {
-                let val = self.evaluate(value);
-                println!("{}", val);
+                let val = self.evaluate(value)?;
+                self.write("stdout", val.to_string());
+            }
+            Statement::Block(statements) => self.execute_scoped(statements)?,
+            Statement::Function(declaration) => {
+                let name = declaration.name.clone().unwrap_or_default();
+                let function = Function::new(
+                    declaration,
+                    Arc::clone(&self.environment),
+                    self.file.clone(),
+                );
+                self.environment.lock().unwrap().define(
+                    name,
+                    Value::Function(Arc::new(function)),
+                    None,
+                );
+            }
+            Statement::Struct { name, fields } => {
+                let structure = Struct::new(name.clone(), fields);
+                self.environment.lock().unwrap().define(
+                    name,
+                    Value::Struct(Arc::new(structure)),
+                    None,
+                );
             }
-            Statement::Block(statements) => {
-                for stmt in statements {
-                    self.execute(stmt)?;
+            Statement::Impl { name, methods } => {
+                let structure = match self.environment.lock().unwrap().get(&name)? {
+                    Value::Struct(structure) => structure,
+                    value => return Err(format!("Cannot implement methods on '{}'", value).into()),
+                };
+                for method in methods {
+                    let method =
+                        Function::new(method, Arc::clone(&self.environment), self.file.clone());
+                    structure.define_method(method.method_of(&structure.name));
                 }
             }
+            Statement::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                let superclass = match superclass {
+                    Some(superclass) => {
+                        let value = self.environment.lock().unwrap().get(&superclass)?;
+                        match value {
+                            Value::Class(class) => Some(class),
+                            _ => {
+                                return Err(
+                                    format!("Superclass '{}' must be a class", superclass).into()
+                                )
+                            }
+                        }
+                    }
+                    None => None,
+                };
+
+                let mut closure = Arc::clone(&self.environment);
+                if let Some(superclass) = &superclass {
+                    let mut environment = Environment::with_enclosing(closure);
+                    environment.define(
+                        "super".to_string(),
+                        Value::Class(Arc::clone(superclass)),
+                        None,
+                    );
+                    closure = Arc::new(Mutex::new(environment));
+                }
+
+                let methods = methods
+                    .into_iter()
+                    .map(|method| {
+                        let function =
+                            Function::new(method, Arc::clone(&closure), self.file.clone())
+                                .method_of(&name);
+                        (function.name().to_string(), Arc::new(function))
+                    })
+                    .collect::<HashMap<_, _>>();
+                let class = Class::new(name.clone(), superclass, methods);
+                self.environment
+                    .lock()
+                    .unwrap()
+                    .define(name, Value::Class(Arc::new(class)), None);
+            }
+            Statement::Enum { name, variants } => {
+                let enumeration = Enum::new(name.clone(), variants);
+                self.environment.lock().unwrap().define(
+                    name,
+                    Value::Enum(Arc::new(enumeration)),
+                    None,
+                );
+            }
+            Statement::Return(value) => {
+                let value = match value {
+                    Some(value) => self.evaluate(value)?,
+                    None => Value::Nil,
+                };
+                return Err(Interrupt::Return(value));
+            }
             Statement::If {
                 condition,
                 then_branch,
                 else_branch,
+                branch,
             } => {
-                if self.evaluate(condition).is_truthy() {
+                if self.evaluate(condition)?.is_truthy() {
+                    self.cover_branch(branch, 0);
                     self.execute(*then_branch)?;
-                } else if let Some(else_branch) = else_branch {
-                    self.execute(*else_branch)?;
+                } else {
+                    self.cover_branch(branch, 1);
+                    if let Some(else_branch) = else_branch {
+                        self.execute(*else_branch)?;
+                    }
                 }
             }
-            Statement::While { condition, body } => {
-                while self.evaluate(condition.clone()).is_truthy() {
+            Statement::While {
+                condition,
+                body,
+                branch,
+            } => {
+                while self.evaluate(condition.clone())?.is_truthy() {
+                    self.cover_branch(branch, 0);
                     self.execute(*body.clone())?;
                 }
+                self.cover_branch(branch, 1);
+            }
+            Statement::Import { path, alias } => {
+                let module = self.import(&path)?;
+                let name = alias.unwrap_or_else(|| module.name.clone());
+                self.environment
+                    .lock()
+                    .unwrap()
+                    .define(name, Value::Module(module), None);
+            }
+            Statement::FromImport { path, names } => {
+                let imported = self.environment.lock().unwrap().get(&path);
+                let module = match imported {
+                    Ok(Value::Module(module)) => module,
+                    _ => self.import(&path)?,
+                };
+                for name in names {
+                    let value = module.get(&name)?;
+                    self.environment.lock().unwrap().define(name, value, None);
+                }
+            }
+            Statement::Export(declaration) => self.execute(*declaration)?,
+            Statement::ExportNames(_) => {}
+            Statement::Throw(value) => {
+                let value = self.evaluate(value)?;
+                return Err(Interrupt::Error(Box::new(Exception::new(value))));
+            }
+            Statement::Try {
+                body,
+                catch,
+                finally,
+            } => self.try_statement(body, catch, finally)?,
+            Statement::Thread(body) => self.spawn_thread(*body),
+            Statement::Select(arms) => self.select(arms)?,
+            Statement::Test { .. } => {}
+            Statement::Located { line, statement } => {
+                self.debug(line);
+                self.cover_line(line);
+                if let Some(profiler) = &mut self.profiler {
+                    profiler.enter_line(self.file.as_deref(), line);
+                }
+                let result = self.execute(*statement);
+                if let Some(profiler) = &mut self.profiler {
+                    profiler.exit_line();
+                }
+                result?;
             }
         }
         Ok(())
     }
 
-    fn evaluate(&mut self, expr: Expression) -> Value {
-        match expr {
+    fn evaluate(&mut self, expr: Expression) -> Result<Value, Interrupt> {
+        let value = match expr {
             Expression::StringLiteral(lit) => Value::StringLiteral(lit),
             Expression::NumberLiteral(num) => Value::NumberLiteral(num),
             Expression::BooleanLiteral(b) => Value::BooleanLiteral(b),
-            Expression::Identifier(name) => self.environment.get(&name).unwrap_or(Value::Nil),
+            Expression::Identifier(name) => {
+                let value = self.environment.lock().unwrap().get(&name);
+                match value {
+                    Ok(value) => value,
+                    Err(error) => result::global(&name).ok_or(error)?,
+                }
+            }
             Expression::Binary {
                 left,
                 operator,
                 right,
             } => {
-                let left = self.evaluate(*left);
-                let right = self.evaluate(*right);
+                let left = self.evaluate(*left)?;
+
+                // These return one of their operands and only evaluate the
+                // right one when the left does not decide the result.
                 match operator {
-                    Operator::Plus => left + right,
-                    Operator::Minus => left - right,
-                    Operator::Star => left * right,
-                    Operator::Slash => left / right,
-                    Operator::Percent => left % right,
-                    Operator::Pow => left.pow(&right),
+                    Operator::And if !left.is_truthy() => return Ok(left),
+                    Operator::Or if left.is_truthy() => return Ok(left),
+                    Operator::Coalesce if left != Value::Nil => return Ok(left),
+                    Operator::And | Operator::Or | Operator::Coalesce => {
+                        return self.evaluate(*right)
+                    }
+                    _ => {}
+                }
 
-                    Operator::And => Value::BooleanLiteral(left.is_truthy() && right.is_truthy()),
-                    Operator::Or => Value::BooleanLiteral(left.is_truthy() || right.is_truthy()),
+                let right = self.evaluate(*right)?;
+                match operator {
+                    Operator::Plus => (left + right)?,
+                    Operator::Minus => (left - right)?,
+                    Operator::Star => (left * right)?,
+                    Operator::Slash => (left / right)?,
+                    Operator::Percent => (left % right)?,
+                    Operator::Pow => left.pow(&right)?,
 
                     Operator::EqualEqual => Value::BooleanLiteral(left == right),
                     Operator::NotEqual => Value::BooleanLiteral(left != right),
@@ -101,26 +689,125 @@ impl Interpreter {
                     Operator::LessThanEqual => Value::BooleanLiteral(left <= right),
                     Operator::GreaterThan => Value::BooleanLiteral(left > right),
                     Operator::GreaterThanEqual => Value::BooleanLiteral(left >= right),
+                    Operator::InstanceOf => Value::BooleanLiteral(left.is_instance_of(&right)?),
 
                     _ => Value::Nil,
                 }
             }
             Expression::Unary { operator, right } => {
-                let right = self.evaluate(*right);
-                match operator {
-                    Operator::Not => Value::BooleanLiteral(!right.is_truthy()),
+                let right = self.evaluate(*right)?;
+                match (operator, right) {
+                    (Operator::Not, right) => Value::BooleanLiteral(!right.is_truthy()),
+                    (Operator::Minus, Value::NumberLiteral(n)) => Value::NumberLiteral(-n),
+                    (Operator::Minus, right) => {
+                        return Err(format!("Cannot negate '{}'", right).into())
+                    }
                     _ => Value::Nil,
                 }
             }
 
+            Expression::Call { callee, arguments } => self.call(*callee, arguments)?,
+            Expression::Get { object, name } => match self.evaluate(*object)? {
+                Value::Instance(instance) => instance.get(&name).ok_or_else(|| {
+                    format!(
+                        "Undefined property '{}' on '{}'",
+                        name, instance.structure.name
+                    )
+                })?,
+                Value::Struct(structure) => match structure.method(&name) {
+                    Some(method) => Value::Function(method),
+                    None => {
+                        return Err(
+                            format!("Undefined method '{}' on '{}'", name, structure.name).into(),
+                        )
+                    }
+                },
+                Value::Object(object) => Object::get(&object, &name).ok_or_else(|| {
+                    format!("Undefined property '{}' on '{}'", name, object.class.name)
+                })?,
+                Value::Enum(enumeration) => {
+                    let variant = EnumValue::new(enumeration, &name, Vec::new())?;
+                    Value::Variant(Arc::new(variant))
+                }
+                Value::Module(module) => module.get(&name)?,
+                Value::Error(exception) => exception
+                    .get(&name)
+                    .ok_or_else(|| format!("Undefined property '{}' on error", name))?,
+                object => {
+                    return Err(format!("Undefined property '{}' on '{}'", name, object).into())
+                }
+            },
+            Expression::Index { object, index } => {
+                let object = self.evaluate(*object)?;
+                let index = self.evaluate(*index)?;
+                builtins::index(&object, &index)?
+            }
+            Expression::List(elements) => {
+                let elements = self.evaluate_arguments(elements)?;
+                Value::List(Arc::new(Mutex::new(elements)))
+            }
+            Expression::StructLiteral { name, fields } => {
+                let structure = match self.environment.lock().unwrap().get(&name)? {
+                    Value::Struct(structure) => structure,
+                    value => return Err(format!("'{}' is not a struct", value).into()),
+                };
+                let mut values = Vec::new();
+                for (field, value) in fields {
+                    values.push((field, self.evaluate(value)?));
+                }
+                Value::Instance(Arc::new(Instance::new(structure, values)?))
+            }
+            Expression::Function(declaration) => Value::Function(Arc::new(Function::new(
+                declaration,
+                Arc::clone(&self.environment),
+                self.file.clone(),
+            ))),
+            Expression::Match { value, arms } => {
+                let value = self.evaluate(*value)?;
+                self.match_arms(value, arms)?
+            }
+            Expression::Block(statements) => {
+                let environment = Environment::with_enclosing(Arc::clone(&self.environment));
+                self.execute_block(statements, Arc::new(Mutex::new(environment)))?;
+                Value::Nil
+            }
+            Expression::This => self.environment.lock().unwrap().get("this")?,
+            Expression::Super(name) => {
+                let (superclass, this) = {
+                    let environment = self.environment.lock().unwrap();
+                    (environment.get("super")?, environment.get("this")?)
+                };
+                let method = match superclass {
+                    Value::Class(superclass) => superclass.find_method(&name).ok_or_else(|| {
+                        format!("Undefined method '{}' on '{}'", name, superclass.name)
+                    })?,
+                    _ => return Err("Invalid use of 'super'".to_string().into()),
+                };
+                Value::Function(Arc::new(method.bind(this)))
+            }
+
+            Expression::Is { value, value_type } => {
+                Value::BooleanLiteral(self.evaluate(*value)?.matches_type(&value_type))
+            }
+            Expression::Propagate(expr) => match self.evaluate(*expr)? {
+                Value::Variant(variant) if result::is_builtin(&variant.enumeration) => {
+                    match result::success(&variant) {
+                        Some(value) => value,
+                        None => return Err(Interrupt::Return(Value::Variant(variant))),
+                    }
+                }
+                value => return Err(format!("Cannot use '?' on '{}'", value).into()),
+            },
+
             Expression::Increment(expr) => {
-                let value = self.evaluate(*expr.clone());
+                let value = self.evaluate(*expr.clone())?;
                 if let Value::NumberLiteral(mut num) = value {
                     num += 1.0;
                     if let Expression::Identifier(name) = *expr {
                         self.environment
-                            .assign(name.to_string(), Value::NumberLiteral(num))
-                            .unwrap();
+                            .lock()
+                            .unwrap()
+                            .assign(name.to_string(), Value::NumberLiteral(num))?;
                     }
                     Value::NumberLiteral(num)
                 } else {
@@ -129,13 +816,14 @@ impl Interpreter {
             }
 
             Expression::Decrement(expr) => {
-                let value = self.evaluate(*expr.clone());
+                let value = self.evaluate(*expr.clone())?;
                 if let Value::NumberLiteral(mut num) = value {
                     num -= 1.0;
                     if let Expression::Identifier(name) = *expr {
                         self.environment
-                            .assign(name, Value::NumberLiteral(num))
-                            .unwrap();
+                            .lock()
+                            .unwrap()
+                            .assign(name, Value::NumberLiteral(num))?;
                     }
                     Value::NumberLiteral(num)
                 } else {
@@ -148,41 +836,406 @@ impl Interpreter {
                 then_branch,
                 elif_branches,
                 else_branch,
+                branch,
             } => {
-                if self.evaluate(*condition).is_truthy() {
-                    self.evaluate(*then_branch)
+                if self.evaluate(*condition)?.is_truthy() {
+                    self.cover_branch(branch, 0);
+                    self.evaluate(*then_branch)?
                 } else {
-                    for (elif_condition, elif_branch) in elif_branches {
-                        if self.evaluate(*elif_condition).is_truthy() {
+                    let elifs = elif_branches.len();
+                    for (arm, (elif_condition, elif_branch)) in
+                        elif_branches.into_iter().enumerate()
+                    {
+                        if self.evaluate(*elif_condition)?.is_truthy() {
+                            self.cover_branch(branch, arm + 1);
                             return self.evaluate(*elif_branch);
                         }
                     }
-                    self.evaluate(*else_branch)
+                    self.cover_branch(branch, elifs + 1);
+                    self.evaluate(*else_branch)?
                 }
             }
 
             Expression::Nil => Value::Nil,
+        };
+        Ok(value)
+    }
+
+    fn match_arms(&mut self, value: Value, arms: Vec<MatchArm>) -> Result<Value, Interrupt> {
+        for arm in arms {
+            let mut bindings = Vec::new();
+            if !pattern::matches(&arm.pattern, &value, &mut bindings)? {
+                continue;
+            }
+
+            let mut environment = Environment::with_enclosing(Arc::clone(&self.environment));
+            for (name, value) in bindings {
+                environment.define(name, value, None);
+            }
+            let environment = Arc::new(Mutex::new(environment));
+            let previous = std::mem::replace(&mut self.environment, environment);
+            let result = self.match_arm(arm);
+            self.environment = previous;
+
+            if let Some(value) = result? {
+                return Ok(value);
+            }
+        }
+
+        Err(format!("No match arm for value '{}'", value).into())
+    }
+
+    // Yields `None` when the arm's guard rejects the value.
+    fn match_arm(&mut self, arm: MatchArm) -> Result<Option<Value>, Interrupt> {
+        if let Some(guard) = arm.guard {
+            if !self.evaluate(guard)?.is_truthy() {
+                return Ok(None);
+            }
+        }
+        Ok(Some(self.evaluate(arm.body)?))
+    }
+
+    fn call(&mut self, callee: Expression, arguments: Vec<Expression>) -> Result<Value, Interrupt> {
+        match callee {
+            Expression::Get { object, name } => {
+                let object = self.evaluate(*object)?;
+                let arguments = self.evaluate_arguments(arguments)?;
+                self.call_method(object, &name, arguments)
+            }
+            Expression::Identifier(name) => {
+                let function = self.environment.lock().unwrap().get(&name);
+                let arguments = self.evaluate_arguments(arguments)?;
+                match function {
+                    Ok(function) => self.call_value(function, arguments),
+                    Err(_) if name == "exit" => {
+                        Err(Interrupt::Exit(builtins::exit_status(arguments)?))
+                    }
+                    Err(_) => Ok(builtins::call_function(&name, arguments)
+                        .unwrap_or_else(|| Err(format!("Undefined function '{}'", name)))?),
+                }
+            }
+            callee => {
+                let function = self.evaluate(callee)?;
+                let arguments = self.evaluate_arguments(arguments)?;
+                self.call_value(function, arguments)
+            }
+        }
+    }
+
+    fn call_method(
+        &mut self,
+        object: Value,
+        name: &str,
+        mut arguments: Vec<Value>,
+    ) -> Result<Value, Interrupt> {
+        match &object {
+            Value::Instance(instance) => {
+                if let Some(field) = instance.get(name) {
+                    return self.call_value(field, arguments);
+                }
+                let method = instance.structure.method(name).ok_or_else(|| {
+                    format!(
+                        "Undefined method '{}' on '{}'",
+                        name, instance.structure.name
+                    )
+                })?;
+                if method.takes_self() {
+                    arguments.insert(0, object.clone());
+                }
+                self.call_function(&method, arguments)
+            }
+            Value::Struct(structure) => match structure.method(name) {
+                Some(method) => self.call_function(&method, arguments),
+                None => Err(format!("Undefined method '{}' on '{}'", name, structure.name).into()),
+            },
+            Value::Object(this) => match Object::get(this, name) {
+                Some(method) => self.call_value(method, arguments),
+                None => Err(format!("Undefined method '{}' on '{}'", name, this.class.name).into()),
+            },
+            Value::Enum(enumeration) => {
+                let variant = EnumValue::new(Arc::clone(enumeration), name, arguments)?;
+                Ok(Value::Variant(Arc::new(variant)))
+            }
+            Value::Module(module) => {
+                let function = module.get(name)?;
+                self.call_value(function, arguments)
+            }
+            Value::Variant(variant) if result::is_builtin(&variant.enumeration) => {
+                self.result_method(variant, name, arguments)
+            }
+            _ => Ok(builtins::call_method(&object, name, arguments)?),
+        }
+    }
+
+    // Methods shared by the built-in `Result` and `Option` values.
+    fn result_method(
+        &mut self,
+        variant: &Arc<EnumValue>,
+        name: &str,
+        arguments: Vec<Value>,
+    ) -> Result<Value, Interrupt> {
+        let expected = match name {
+            "unwrap_or" | "map" => 1,
+            _ => 0,
+        };
+        if arguments.len() != expected {
+            return Err(format!(
+                "Expected {} arguments in '{}', found {}",
+                expected,
+                name,
+                arguments.len()
+            )
+            .into());
+        }
+
+        let is_result = Arc::ptr_eq(&variant.enumeration, result::result_enum());
+        let value = result::success(variant);
+        let mut arguments = arguments.into_iter();
+        match (name, is_result) {
+            ("is_ok", true) | ("is_some", false) => Ok(Value::BooleanLiteral(value.is_some())),
+            ("is_err", true) | ("is_none", false) => Ok(Value::BooleanLiteral(value.is_none())),
+            ("unwrap", _) => value.ok_or_else(|| {
+                let variant = Value::Variant(Arc::clone(variant));
+                format!("Called 'unwrap' on '{}'", variant).into()
+            }),
+            ("unwrap_or", _) => Ok(value.unwrap_or_else(|| arguments.next().unwrap())),
+            ("map", _) => match value {
+                Some(value) => {
+                    let mapped = self.call_value(arguments.next().unwrap(), vec![value])?;
+                    Ok(result::wrap_like(variant, mapped))
+                }
+                None => Ok(Value::Variant(Arc::clone(variant))),
+            },
+            _ => Err(format!(
+                "Undefined method '{}' on '{}'",
+                name, variant.enumeration.name
+            )
+            .into()),
+        }
+    }
+
+    pub fn call_value(&mut self, callee: Value, arguments: Vec<Value>) -> Result<Value, Interrupt> {
+        match callee {
+            Value::Function(function) => self.call_function(&function, arguments),
+            Value::Class(class) => self.construct(class, arguments),
+            value => Err(format!("Value '{}' is not callable", value).into()),
+        }
+    }
+
+    fn construct(&mut self, class: Arc<Class>, arguments: Vec<Value>) -> Result<Value, Interrupt> {
+        let object = Value::Object(Arc::new(Object::new(Arc::clone(&class))));
+
+        match class.find_method("init") {
+            Some(initializer) => {
+                self.call_function(&initializer.bind(object.clone()), arguments)?;
+            }
+            None if !arguments.is_empty() => {
+                return Err(format!(
+                    "Expected 0 arguments in '{}', found {}",
+                    class.name,
+                    arguments.len()
+                )
+                .into())
+            }
+            None => {}
+        }
+
+        Ok(object)
+    }
+
+    fn call_function(
+        &mut self,
+        function: &Function,
+        arguments: Vec<Value>,
+    ) -> Result<Value, Interrupt> {
+        if arguments.len() != function.arity() {
+            return Err(format!(
+                "Expected {} arguments in '{}', found {}",
+                function.arity(),
+                function.name(),
+                arguments.len()
+            )
+            .into());
+        }
+
+        let mut environment = Environment::with_enclosing(Arc::clone(&function.closure));
+        for (parameter, argument) in function.declaration.parameters.iter().zip(arguments) {
+            if let Some(expected_type) = &parameter.value_type {
+                if !argument.matches_type(expected_type) {
+                    return Err(format!(
+                        "Type mismatch for parameter '{}' in '{}'",
+                        parameter.name,
+                        function.name()
+                    )
+                    .into());
+                }
+            }
+            environment.define(
+                parameter.name.clone(),
+                argument,
+                parameter.value_type.clone(),
+            );
+        }
+
+        let environment = Arc::new(Mutex::new(environment));
+        if let Some(debugger) = &mut self.debugger {
+            debugger.push_frame(function.name().to_string(), Arc::clone(&environment));
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter_function(FunctionId::of(function));
+        }
+        let body = function.declaration.body.clone();
+        let caller = match &function.file {
+            Some(file) => self.file.replace(file.clone()),
+            None => self.file.clone(),
+        };
+        let result = self.execute_block(body, environment);
+        self.file = caller;
+        if let Some(debugger) = &mut self.debugger {
+            debugger.pop_frame();
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.exit_function();
+        }
+
+        let value = match result {
+            Ok(()) => Value::Nil,
+            Err(Interrupt::Return(value)) => value,
+            Err(Interrupt::Error(mut exception)) => {
+                exception.trace.push(function.name().to_string());
+                return Err(Interrupt::Error(exception));
+            }
+            Err(exit) => return Err(exit),
+        };
+
+        if let Some(expected_type) = &function.declaration.return_type {
+            if !value.matches_type(expected_type) {
+                return Err(
+                    format!("Type mismatch for return value of '{}'", function.name()).into(),
+                );
+            }
         }
+
+        Ok(value)
+    }
+
+    fn evaluate_arguments(&mut self, arguments: Vec<Expression>) -> Result<Vec<Value>, Interrupt> {
+        arguments
+            .into_iter()
+            .map(|argument| self.evaluate(argument))
+            .collect()
     }
 }
 
 impl Value {
-    fn is_truthy(&self) -> bool {
+    pub fn is_truthy(&self) -> bool {
         match self {
             Value::NumberLiteral(n) => *n != 0.0,
             Value::StringLiteral(s) => !s.is_empty(),
             Value::BooleanLiteral(b) => *b,
+            Value::List(elements) => !elements.lock().unwrap().is_empty(),
+            Value::Function(_)
+            | Value::Struct(_)
+            | Value::Instance(_)
+            | Value::Class(_)
+            | Value::Object(_)
+            | Value::Enum(_)
+            | Value::Variant(_)
+            | Value::Channel(_)
+            | Value::Sync(_)
+            | Value::Module(_)
+            | Value::Error(_) => true,
             Value::Nil => false,
         }
     }
 }
 
+impl Value {
+    /// The value as it is shown inside a list, with strings quoted.
+    pub fn repr(&self) -> String {
+        match self {
+            Value::StringLiteral(s) => format!("{:?}", s),
+            value => value.to_string(),
+        }
+    }
+
+    // Strings nested inside other values are quoted so they can be told apart.
+    fn fmt_nested(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::StringLiteral(s) => write!(f, "{:?}", s),
+            value => write!(f, "{}", value),
+        }
+    }
+}
+
+thread_local! {
+    // The lists being printed on this thread, so that a list containing
+    // itself prints as `[...]` instead of recursing forever.
+    static PRINTING_LISTS: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Value::StringLiteral(s) => write!(f, "{}", s),
             Value::NumberLiteral(n) => write!(f, "{}", n),
             Value::BooleanLiteral(b) => write!(f, "{}", b),
+            Value::List(list) => {
+                let id = Arc::as_ptr(list) as usize;
+                if PRINTING_LISTS.with(|printing| printing.borrow().contains(&id)) {
+                    return write!(f, "[...]");
+                }
+                // The lock is released first, as an element may be this list.
+                let elements = list.lock().unwrap().clone();
+                PRINTING_LISTS.with(|printing| printing.borrow_mut().push(id));
+                let result = (|| {
+                    write!(f, "[")?;
+                    for (i, element) in elements.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        element.fmt_nested(f)?;
+                    }
+                    write!(f, "]")
+                })();
+                PRINTING_LISTS.with(|printing| printing.borrow_mut().pop());
+                result
+            }
+            Value::Function(function) => write!(f, "<func {}>", function.name()),
+            Value::Struct(structure) => write!(f, "<struct {}>", structure.name),
+            Value::Instance(instance) => {
+                write!(f, "{} {{", instance.structure.name)?;
+                for (i, (name, value)) in instance.values().iter().enumerate() {
+                    write!(f, "{}{}: ", if i > 0 { ", " } else { " " }, name)?;
+                    value.fmt_nested(f)?;
+                }
+                write!(f, " }}")
+            }
+            Value::Class(class) => write!(f, "<class {}>", class.name),
+            Value::Object(object) => write!(f, "<{} instance>", object.class.name),
+            Value::Enum(enumeration) => write!(f, "<enum {}>", enumeration.name),
+            Value::Variant(variant) => {
+                // `Ok(1)` and `None` read better without their enum's name.
+                if !result::is_builtin(&variant.enumeration) {
+                    write!(f, "{}.", variant.enumeration.name)?;
+                }
+                write!(f, "{}", variant.variant)?;
+                if !variant.values.is_empty() {
+                    write!(f, "(")?;
+                    for (i, value) in variant.values.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        value.fmt_nested(f)?;
+                    }
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+            Value::Channel(_) => write!(f, "<channel>"),
+            Value::Sync(_) => write!(f, "<sync>"),
+            Value::Module(module) => write!(f, "<module {}>", module.name),
+            Value::Error(exception) => write!(f, "Error: {}", exception.message),
             Value::Nil => write!(f, "nil"),
         }
     }
@@ -1,2 +1,18 @@
+pub mod builtins;
+pub mod class;
+pub mod concurrency;
+pub mod coverage;
+pub mod debugger;
+pub mod enumeration;
 pub mod environment;
+pub mod exception;
+pub mod function;
+#[allow(clippy::module_inception)]
 pub mod interpreter;
+pub mod module;
+pub mod pattern;
+pub mod profiler;
+pub mod result;
+pub mod structs;
+#[cfg(test)]
+mod tests;
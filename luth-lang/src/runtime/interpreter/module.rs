@@ -0,0 +1,185 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    env,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::project::manifest::Manifest;
+use crate::runtime::interpreter::environment::{Environment, Value};
+use crate::runtime::parser::ast::Statement;
+
+pub const EXTENSION: &str = "lt";
+
+/// A loaded module. Only names listed with `export` can be read from it.
+#[derive(Debug)]
+pub struct Module {
+    pub name: String,
+    pub path: PathBuf,
+    environment: Arc<Mutex<Environment>>,
+    exports: HashSet<String>,
+}
+
+impl Module {
+    pub fn new(
+        path: PathBuf,
+        environment: Arc<Mutex<Environment>>,
+        exports: HashSet<String>,
+    ) -> Self {
+        Module {
+            name: module_name(&path.to_string_lossy()),
+            path,
+            environment,
+            exports,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<Value, String> {
+        if !self.exports.contains(name) {
+            return Err(format!("Module '{}' does not export '{}'", self.name, name));
+        }
+        self.environment.lock().unwrap().get(name)
+    }
+}
+
+/// Finds module files and keeps every module that has been loaded, so each
+/// file is executed at most once per program.
+#[derive(Debug)]
+pub struct ModuleLoader {
+    search_path: Vec<PathBuf>,
+    // The source directories of each dependency of the project.
+    packages: BTreeMap<String, Vec<PathBuf>>,
+    cache: Mutex<HashMap<PathBuf, Arc<Module>>>,
+}
+
+impl ModuleLoader {
+    /// The search path is read from `LUTH_PATH`, followed by the source
+    /// directories of the project in the working directory and the working
+    /// directory itself. A manifest that does not load is left for the
+    /// commands that read it to report.
+    pub fn from_env() -> Self {
+        let mut search_path: Vec<PathBuf> = env::var_os("LUTH_PATH")
+            .map(|paths| env::split_paths(&paths).collect())
+            .unwrap_or_default();
+        let mut packages = BTreeMap::new();
+        if let Ok(Some(manifest)) = Manifest::find() {
+            search_path.extend(manifest.source_dirs());
+            packages = manifest.packages();
+        }
+        if let Ok(current_dir) = env::current_dir() {
+            search_path.push(current_dir);
+        }
+
+        ModuleLoader {
+            search_path,
+            packages,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Resolves `path` relative to the directory of the importing file
+    /// first, then inside the dependency it starts with, as in
+    /// `package/module`, then against each entry of the search path.
+    pub fn resolve(&self, path: &str, importer: Option<&Path>) -> Result<PathBuf, String> {
+        let mut relative = PathBuf::from(path);
+        if relative.extension().is_none() {
+            relative.set_extension(EXTENSION);
+        }
+
+        let base = importer.and_then(Path::parent);
+        let mut components = relative.components();
+        let package = components
+            .next()
+            .and_then(|name| self.packages.get(name.as_os_str().to_str()?))
+            .into_iter()
+            .flatten()
+            .map(|directory| directory.join(components.as_path()));
+        let candidates = base
+            .into_iter()
+            .map(|directory| directory.join(&relative))
+            .chain(package)
+            .chain(
+                self.search_path
+                    .iter()
+                    .map(|directory| directory.join(&relative)),
+            );
+
+        candidates
+            .into_iter()
+            .find(|candidate| candidate.is_file())
+            .and_then(|candidate| candidate.canonicalize().ok())
+            .ok_or_else(|| format!("Module '{}' not found", path))
+    }
+
+    pub fn cached(&self, path: &Path) -> Option<Arc<Module>> {
+        self.cache.lock().unwrap().get(path).cloned()
+    }
+
+    pub fn insert(&self, module: Arc<Module>) {
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(module.path.clone(), module);
+    }
+}
+
+/// The name `import "path"` binds when no alias is given: the file name
+/// without its extension.
+pub fn module_name(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// Names made visible by the `export` statements of a module.
+pub fn exported_names(statements: &[Statement]) -> HashSet<String> {
+    let mut names = HashSet::new();
+
+    for statement in statements {
+        match statement {
+            Statement::Export(declaration) => {
+                if let Some(name) = declared_name(declaration) {
+                    names.insert(name.to_string());
+                }
+            }
+            Statement::ExportNames(exported) => names.extend(exported.iter().cloned()),
+            Statement::Located { statement, .. } => {
+                names.extend(exported_names(std::slice::from_ref(statement)));
+            }
+            _ => {}
+        }
+    }
+
+    names
+}
+
+/// The paths a program's top level imports, as written.
+pub fn imported_paths(statements: &[Statement]) -> Vec<String> {
+    let mut paths = Vec::new();
+
+    for statement in statements {
+        match statement {
+            Statement::Import { path, .. } | Statement::FromImport { path, .. } => {
+                paths.push(path.clone());
+            }
+            Statement::Located { statement, .. } => {
+                paths.extend(imported_paths(std::slice::from_ref(statement)));
+            }
+            _ => {}
+        }
+    }
+
+    paths
+}
+
+fn declared_name(statement: &Statement) -> Option<&str> {
+    match statement {
+        Statement::VariableDeclaration { name, .. }
+        | Statement::Struct { name, .. }
+        | Statement::Class { name, .. }
+        | Statement::Enum { name, .. } => Some(name),
+        Statement::Function(declaration) => declaration.name.as_deref(),
+        _ => None,
+    }
+}
@@ -0,0 +1,115 @@
+use std::sync::{Arc, Mutex};
+
+use crate::runtime::interpreter::environment::Value;
+use crate::runtime::parser::ast::{Expression, Pattern};
+
+/// Tests `value` against `pattern`, collecting the variables it binds.
+/// Bindings are only meaningful when the match succeeds.
+pub fn matches(
+    pattern: &Pattern,
+    value: &Value,
+    bindings: &mut Vec<(String, Value)>,
+) -> Result<bool, String> {
+    match (pattern, value) {
+        (Pattern::Wildcard, _) => Ok(true),
+        (Pattern::Binding(name), value) => {
+            bindings.push((name.clone(), value.clone()));
+            Ok(true)
+        }
+        (Pattern::Literal(literal), value) => Ok(literal_value(literal)? == *value),
+        (
+            Pattern::Range {
+                start,
+                end,
+                inclusive,
+            },
+            Value::NumberLiteral(n),
+        ) => Ok(*n >= *start && if *inclusive { *n <= *end } else { *n < *end }),
+        (
+            Pattern::Variant {
+                enum_name,
+                variant,
+                fields,
+            },
+            Value::Variant(value),
+        ) => {
+            if enum_name
+                .as_ref()
+                .is_some_and(|name| *name != value.enumeration.name)
+                || *variant != value.variant
+            {
+                return Ok(false);
+            }
+            if fields.len() != value.values.len() {
+                return Err(format!(
+                    "Pattern for '{}.{}' expects {} values, found {}",
+                    value.enumeration.name,
+                    variant,
+                    value.values.len(),
+                    fields.len()
+                ));
+            }
+            all_match(fields.iter().zip(&value.values), bindings)
+        }
+        (Pattern::List { elements, rest }, Value::List(list)) => {
+            let list = list.lock().unwrap().clone();
+            let length_matches = match rest {
+                Some(_) => list.len() >= elements.len(),
+                None => list.len() == elements.len(),
+            };
+            if !length_matches || !all_match(elements.iter().zip(&list), bindings)? {
+                return Ok(false);
+            }
+
+            if let Some(Some(name)) = rest {
+                let remaining = list[elements.len()..].to_vec();
+                bindings.push((name.clone(), Value::List(Arc::new(Mutex::new(remaining)))));
+            }
+            Ok(true)
+        }
+        (Pattern::Struct { name, fields }, Value::Instance(instance)) => {
+            if *name != instance.structure.name {
+                return Ok(false);
+            }
+            let mut values = Vec::new();
+            for (field, _) in fields {
+                match instance.get(field) {
+                    Some(value) => values.push(value),
+                    None => {
+                        return Err(format!(
+                            "Struct '{}' has no field '{}'",
+                            instance.structure.name, field
+                        ))
+                    }
+                }
+            }
+            all_match(
+                fields.iter().map(|(_, pattern)| pattern).zip(&values),
+                bindings,
+            )
+        }
+        _ => Ok(false),
+    }
+}
+
+fn all_match<'a>(
+    pairs: impl Iterator<Item = (&'a Pattern, &'a Value)>,
+    bindings: &mut Vec<(String, Value)>,
+) -> Result<bool, String> {
+    for (pattern, value) in pairs {
+        if !matches(pattern, value, bindings)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn literal_value(literal: &Expression) -> Result<Value, String> {
+    match literal {
+        Expression::StringLiteral(s) => Ok(Value::StringLiteral(s.clone())),
+        Expression::NumberLiteral(n) => Ok(Value::NumberLiteral(*n)),
+        Expression::BooleanLiteral(b) => Ok(Value::BooleanLiteral(*b)),
+        Expression::Nil => Ok(Value::Nil),
+        _ => Err("Invalid literal pattern".to_string()),
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::runtime::interpreter::function::Function;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LineStats {
+    pub hits: u64,
+    // Time spent in statements starting on the line, including the calls
+    // they make.
+    pub time: Duration,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FunctionStats {
+    pub calls: u64,
+    // Time spent in the function's own statements.
+    pub self_time: Duration,
+    // Time spent from entering the function until it returned, counted
+    // once for recursive calls.
+    pub total_time: Duration,
+}
+
+/// A function as the profiler tells it apart: by name, qualified with its
+/// type for methods, and by where it was declared, so that lambdas and
+/// same-named functions in different files are kept apart.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FunctionId {
+    pub name: String,
+    pub file: Option<PathBuf>,
+    pub line: usize,
+}
+
+impl FunctionId {
+    pub fn of(function: &Function) -> Self {
+        FunctionId {
+            name: function.qualified_name(),
+            file: function.file.clone(),
+            line: function.declaration.line,
+        }
+    }
+
+    fn main() -> Self {
+        FunctionId {
+            name: "main".to_string(),
+            file: None,
+            line: 0,
+        }
+    }
+
+    /// The name followed by where the function was declared, as in
+    /// `area@src/shapes.lt:3`, with files shown relative to `base`. The
+    /// top level, which was not declared anywhere, is just `main`.
+    pub fn label(&self, base: &Path) -> String {
+        match (&self.file, self.line) {
+            (_, 0) => self.name.clone(),
+            (Some(file), line) => {
+                let file = file.strip_prefix(base).unwrap_or(file);
+                format!("{}@{}:{}", self.name, file.display(), line)
+            }
+            (None, line) => format!("{}@{}", self.name, line),
+        }
+    }
+}
+
+struct Call {
+    function: FunctionId,
+    started: Instant,
+    // Time spent in the calls this one made.
+    children: Duration,
+}
+
+/// A line of a file, or of a program read from elsewhere when there is no
+/// file.
+pub type Location = (Option<PathBuf>, usize);
+
+/// Timings collected while a program runs, by line and by function. The
+/// top level counts as a function called `main`.
+pub struct Profiler {
+    pub lines: BTreeMap<Location, LineStats>,
+    pub functions: HashMap<FunctionId, FunctionStats>,
+    // Self time of each call stack seen, outermost function first.
+    pub stacks: HashMap<Vec<FunctionId>, Duration>,
+    running_lines: Vec<(Location, Instant)>,
+    calls: Vec<Call>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        let mut profiler = Profiler {
+            lines: BTreeMap::new(),
+            functions: HashMap::new(),
+            stacks: HashMap::new(),
+            running_lines: Vec::new(),
+            calls: Vec::new(),
+        };
+        profiler.enter_function(FunctionId::main());
+        profiler
+    }
+
+    pub fn enter_line(&mut self, file: Option<&Path>, line: usize) {
+        let location = (file.map(Path::to_path_buf), line);
+        self.lines.entry(location.clone()).or_default().hits += 1;
+        self.running_lines.push((location, Instant::now()));
+    }
+
+    pub fn exit_line(&mut self) {
+        let Some((location, started)) = self.running_lines.pop() else {
+            return;
+        };
+        // A line that is still running further out, as in recursion, gets
+        // its time when that outer run ends.
+        if self
+            .running_lines
+            .iter()
+            .all(|(running, _)| *running != location)
+        {
+            self.lines.entry(location).or_default().time += started.elapsed();
+        }
+    }
+
+    pub fn enter_function(&mut self, function: FunctionId) {
+        self.calls.push(Call {
+            function,
+            started: Instant::now(),
+            children: Duration::ZERO,
+        });
+    }
+
+    pub fn exit_function(&mut self) {
+        let Some(call) = self.calls.pop() else {
+            return;
+        };
+        let total = call.started.elapsed();
+        let self_time = total.saturating_sub(call.children);
+        if let Some(caller) = self.calls.last_mut() {
+            caller.children += total;
+        }
+
+        let recursive = self
+            .calls
+            .iter()
+            .any(|outer| outer.function == call.function);
+        let stats = self.functions.entry(call.function.clone()).or_default();
+        stats.calls += 1;
+        stats.self_time += self_time;
+        if !recursive {
+            stats.total_time += total;
+        }
+
+        let mut stack: Vec<FunctionId> = self
+            .calls
+            .iter()
+            .map(|outer| outer.function.clone())
+            .collect();
+        stack.push(call.function);
+        *self.stacks.entry(stack).or_default() += self_time;
+    }
+
+    /// Ends the `main` frame once the program is done.
+    pub fn finish(&mut self) {
+        while !self.calls.is_empty() {
+            self.exit_function();
+        }
+    }
+
+    /// Tables of the lines and functions that took the most time. Files are
+    /// shown relative to `base`, and a program without a file as `-`.
+    pub fn report(&self, base: &Path) -> String {
+        let mut report = String::new();
+
+        let mut lines: Vec<_> = self.lines.iter().collect();
+        lines.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.time));
+        writeln!(
+            report,
+            "{:<32} {:>8} {:>10} {:>12}",
+            "file", "line", "hits", "time (ms)"
+        )
+        .unwrap();
+        for ((file, line), stats) in lines {
+            let name = match file {
+                Some(file) => file
+                    .strip_prefix(base)
+                    .unwrap_or(file)
+                    .display()
+                    .to_string(),
+                None => "-".to_string(),
+            };
+            writeln!(
+                report,
+                "{:<32} {:>8} {:>10} {:>12.3}",
+                name,
+                line,
+                stats.hits,
+                milliseconds(stats.time)
+            )
+            .unwrap();
+        }
+
+        let mut functions: Vec<_> = self.functions.iter().collect();
+        functions.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.self_time));
+        writeln!(
+            report,
+            "\n{:<40} {:>10} {:>12} {:>12}",
+            "function", "calls", "self (ms)", "total (ms)"
+        )
+        .unwrap();
+        for (function, stats) in functions {
+            writeln!(
+                report,
+                "{:<40} {:>10} {:>12.3} {:>12.3}",
+                function.label(base),
+                stats.calls,
+                milliseconds(stats.self_time),
+                milliseconds(stats.total_time)
+            )
+            .unwrap();
+        }
+        report
+    }
+
+    /// One `outer;inner microseconds` line per call stack, the collapsed
+    /// format that flame graph tools read. Functions are labeled as in
+    /// `report`.
+    pub fn collapsed_stacks(&self, base: &Path) -> String {
+        let mut stacks: Vec<String> = self
+            .stacks
+            .iter()
+            .map(|(stack, time)| {
+                let names: Vec<String> = stack
+                    .iter()
+                    .map(|function| function.label(base).replace([';', ' '], "_"))
+                    .collect();
+                format!("{} {}\n", names.join(";"), time.as_micros())
+            })
+            .collect();
+        stacks.sort();
+        stacks.concat()
+    }
+}
+
+fn milliseconds(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,78 @@
+use std::sync::{Arc, OnceLock};
+
+use crate::runtime::interpreter::enumeration::{Enum, EnumValue};
+use crate::runtime::interpreter::environment::Value;
+use crate::runtime::parser::ast::Variant;
+
+static RESULT: OnceLock<Arc<Enum>> = OnceLock::new();
+static OPTION: OnceLock<Arc<Enum>> = OnceLock::new();
+
+/// The built-in `Result` enum, with variants `Ok(value)` and `Err(error)`.
+pub fn result_enum() -> &'static Arc<Enum> {
+    RESULT.get_or_init(|| builtin_enum("Result", [("Ok", true), ("Err", true)]))
+}
+
+/// The built-in `Option` enum, with variants `Some(value)` and `None`.
+pub fn option_enum() -> &'static Arc<Enum> {
+    OPTION.get_or_init(|| builtin_enum("Option", [("Some", true), ("None", false)]))
+}
+
+fn builtin_enum<const N: usize>(name: &str, variants: [(&str, bool); N]) -> Arc<Enum> {
+    let variants = variants
+        .into_iter()
+        .map(|(name, has_value)| Variant {
+            name: name.to_string(),
+            fields: if has_value {
+                vec!["value".to_string()]
+            } else {
+                Vec::new()
+            },
+        })
+        .collect();
+    Arc::new(Enum::new(name.to_string(), variants))
+}
+
+pub fn is_builtin(enumeration: &Arc<Enum>) -> bool {
+    Arc::ptr_eq(enumeration, result_enum()) || Arc::ptr_eq(enumeration, option_enum())
+}
+
+/// Names that are always defined: the two enums and the `None` value.
+pub fn global(name: &str) -> Option<Value> {
+    match name {
+        "Result" => Some(Value::Enum(Arc::clone(result_enum()))),
+        "Option" => Some(Value::Enum(Arc::clone(option_enum()))),
+        "None" => Some(variant(option_enum(), "None", Vec::new())),
+        _ => None,
+    }
+}
+
+/// Builds `Ok(..)`, `Err(..)` or `Some(..)` when called by name.
+pub fn construct(name: &str, arguments: Vec<Value>) -> Option<Result<Value, String>> {
+    let enumeration = match name {
+        "Ok" | "Err" => result_enum(),
+        "Some" => option_enum(),
+        _ => return None,
+    };
+    let value = EnumValue::new(Arc::clone(enumeration), name, arguments);
+    Some(value.map(|value| Value::Variant(Arc::new(value))))
+}
+
+pub fn wrap_like(original: &EnumValue, value: Value) -> Value {
+    variant(&original.enumeration, &original.variant, vec![value])
+}
+
+/// The wrapped value of an `Ok` or `Some`; `None` for `Err` and `None`.
+pub fn success(value: &EnumValue) -> Option<Value> {
+    match value.variant.as_str() {
+        "Ok" | "Some" => value.values.first().cloned(),
+        _ => None,
+    }
+}
+
+fn variant(enumeration: &Arc<Enum>, name: &str, values: Vec<Value>) -> Value {
+    Value::Variant(Arc::new(EnumValue {
+        enumeration: Arc::clone(enumeration),
+        variant: name.to_string(),
+        values,
+    }))
+}
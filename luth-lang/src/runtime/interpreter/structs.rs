@@ -0,0 +1,113 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::runtime::interpreter::environment::Value;
+use crate::runtime::interpreter::function::Function;
+use crate::runtime::parser::ast::Parameter;
+
+#[derive(Debug)]
+pub struct Struct {
+    pub name: String,
+    pub fields: Vec<Parameter>,
+    methods: Mutex<HashMap<String, Arc<Function>>>,
+}
+
+impl Struct {
+    pub fn new(name: String, fields: Vec<Parameter>) -> Self {
+        Struct {
+            name,
+            fields,
+            methods: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn define_method(&self, function: Function) {
+        let name = function.name().to_string();
+        self.methods
+            .lock()
+            .unwrap()
+            .insert(name, Arc::new(function));
+    }
+
+    pub fn method(&self, name: &str) -> Option<Arc<Function>> {
+        self.methods.lock().unwrap().get(name).cloned()
+    }
+
+    fn field(&self, name: &str) -> Result<&Parameter, String> {
+        self.fields
+            .iter()
+            .find(|field| field.name == name)
+            .ok_or_else(|| format!("Struct '{}' has no field '{}'", self.name, name))
+    }
+
+    fn check_field(&self, name: &str, value: &Value) -> Result<(), String> {
+        match &self.field(name)?.value_type {
+            Some(expected_type) if !value.matches_type(expected_type) => Err(format!(
+                "Type mismatch for field '{}' of '{}'",
+                name, self.name
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    pub structure: Arc<Struct>,
+    fields: Mutex<HashMap<String, Value>>,
+}
+
+impl Instance {
+    /// Builds an instance from a struct literal. Every declared field has to
+    /// be given exactly once, with a value matching its declared type.
+    pub fn new(structure: Arc<Struct>, values: Vec<(String, Value)>) -> Result<Self, String> {
+        let mut fields = HashMap::new();
+        for (name, value) in values {
+            structure.check_field(&name, &value)?;
+            if fields.insert(name.clone(), value).is_some() {
+                return Err(format!(
+                    "Field '{}' of '{}' given more than once",
+                    name, structure.name
+                ));
+            }
+        }
+
+        if let Some(missing) = structure
+            .fields
+            .iter()
+            .find(|field| !fields.contains_key(&field.name))
+        {
+            return Err(format!(
+                "Missing field '{}' in '{}'",
+                missing.name, structure.name
+            ));
+        }
+
+        Ok(Instance {
+            structure,
+            fields: Mutex::new(fields),
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.fields.lock().unwrap().get(name).cloned()
+    }
+
+    pub fn set(&self, name: &str, value: Value) -> Result<(), String> {
+        self.structure.check_field(name, &value)?;
+        self.fields.lock().unwrap().insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// Field values in declaration order.
+    pub fn values(&self) -> Vec<(String, Value)> {
+        let fields = self.fields.lock().unwrap();
+        self.structure
+            .fields
+            .iter()
+            .map(|field| (field.name.clone(), fields[&field.name].clone()))
+            .collect()
+    }
+}
@@ -0,0 +1,376 @@
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use std::{env, fs, process};
+
+use crate::runtime::interpreter::concurrency::{select, Channel, Selected};
+
+use crate::runtime::interpreter::debugger::Event;
+use crate::runtime::interpreter::environment::Value;
+use crate::runtime::interpreter::interpreter::Interpreter;
+use crate::runtime::interpreter::module::imported_paths;
+use crate::runtime::interpreter::profiler::{FunctionStats, Profiler};
+use crate::runtime::lexer::lexer::{lexer, lexer_with_lines};
+use crate::runtime::parser::parser::Parser;
+
+// The stats of the function labeled `label`, as in `fib@1`.
+fn function(profiler: &Profiler, label: &str) -> FunctionStats {
+    let stats = profiler
+        .functions
+        .iter()
+        .find(|(function, _)| function.label(Path::new("")) == label);
+    *stats.unwrap().1
+}
+
+fn profile(source: &str) -> Profiler {
+    let (tokens, lines) = lexer_with_lines(source);
+    let statements = Parser::with_lines(tokens, lines).parse().unwrap();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_profiler(Profiler::new());
+    interpreter.interpret(statements);
+    interpreter.take_profiler().unwrap()
+}
+
+const FIBONACCI: &str = "\
+func fib(n) {
+    if n < 2 {
+        return n;
+    }
+    return fib(n - 1) + fib(n - 2);
+}
+func run() {
+    return fib(5);
+}
+var i = 0;
+while i < 2 {
+    run();
+    i++;
+}
+";
+
+#[test]
+fn counts_line_hits_and_function_calls() {
+    let profiler = profile(FIBONACCI);
+
+    let hits: Vec<(usize, u64)> = profiler
+        .lines
+        .iter()
+        .map(|((_, line), stats)| (*line, stats.hits))
+        .collect();
+    // fib(5) makes 15 calls, 8 of which reach the base case.
+    assert_eq!(
+        hits,
+        vec![
+            (1, 1),
+            (2, 30),
+            (3, 16),
+            (5, 14),
+            (7, 1),
+            (8, 2),
+            (10, 1),
+            (11, 1),
+            (12, 2),
+            (13, 2)
+        ]
+    );
+    assert_eq!(function(&profiler, "fib@1").calls, 30);
+    assert_eq!(function(&profiler, "run@7").calls, 2);
+    assert_eq!(function(&profiler, "main").calls, 1);
+
+    let main = function(&profiler, "main");
+    let fib = function(&profiler, "fib@1");
+    assert!(main.total_time >= function(&profiler, "run@7").total_time);
+    assert!(fib.self_time <= fib.total_time);
+}
+
+#[test]
+fn collapsed_stacks_name_every_caller() {
+    let profiler = profile(FIBONACCI);
+    let collapsed = profiler.collapsed_stacks(Path::new(""));
+    let stacks: Vec<&str> = collapsed
+        .lines()
+        .map(|line| line.rsplit_once(' ').unwrap().0)
+        .collect();
+
+    assert_eq!(stacks[0], "main");
+    assert_eq!(stacks[1], "main;run@7");
+    assert_eq!(stacks[2], "main;run@7;fib@1");
+    assert_eq!(
+        stacks.last(),
+        Some(&"main;run@7;fib@1;fib@1;fib@1;fib@1;fib@1")
+    );
+}
+
+#[test]
+fn keeps_methods_and_lambdas_apart() {
+    let profiler = profile(
+        "\
+class A {
+    speak() { return 1; }
+}
+class B {
+    speak() { return 2; }
+}
+var f = |x| x + 1;
+var g = |x| x * 2;
+A().speak();
+B().speak();
+f(1);
+g(2);
+",
+    );
+    let mut labels: Vec<String> = profiler
+        .functions
+        .iter()
+        .map(|(function, stats)| format!("{} {}", function.label(Path::new("")), stats.calls))
+        .collect();
+    labels.sort();
+    assert_eq!(
+        labels,
+        vec![
+            "A.speak@2 1",
+            "B.speak@5 1",
+            "anonymous@7 1",
+            "anonymous@8 1",
+            "main 1"
+        ]
+    );
+}
+
+// Runs `source` with `args`, returning its exit status and what it printed.
+#[test]
+fn keeps_the_lines_of_imported_modules_apart() {
+    let dir = env::temp_dir().join(format!("luth-profile-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("shapes.lt"),
+        "export func area(w, h) {\n    return w * h;\n}\n",
+    )
+    .unwrap();
+    let source = "import \"shapes\";\nvar a = shapes.area(2, 3);\n";
+    fs::write(dir.join("main.lt"), source).unwrap();
+
+    let (tokens, lines) = lexer_with_lines(source);
+    let statements = Parser::with_lines(tokens, lines).parse().unwrap();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_script_path(&dir.join("main.lt"));
+    interpreter.set_profiler(Profiler::new());
+    interpreter.interpret(statements);
+    let profiler = interpreter.take_profiler().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let hits: Vec<(String, usize, u64)> = profiler
+        .lines
+        .iter()
+        .map(|((file, line), stats)| {
+            let name = file.as_ref().unwrap().file_name().unwrap();
+            (name.to_string_lossy().into_owned(), *line, stats.hits)
+        })
+        .collect();
+    assert_eq!(
+        hits,
+        vec![
+            ("main.lt".to_string(), 1, 1),
+            ("main.lt".to_string(), 2, 1),
+            ("shapes.lt".to_string(), 2, 1),
+        ]
+    );
+}
+
+fn run(source: &str, args: &[&str]) -> (i32, String) {
+    let statements = Parser::new(lexer(source)).parse().unwrap();
+    let (output, printed) = mpsc::channel();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(output);
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    interpreter.set_args(&args);
+    let status = interpreter.interpret(statements);
+
+    let printed = printed
+        .try_iter()
+        .filter_map(|event| match event {
+            Event::Output { text, .. } => Some(text),
+            _ => None,
+        })
+        .collect();
+    (status, printed)
+}
+
+#[test]
+fn exit_status_reflects_errors_and_exit_calls() {
+    assert_eq!(
+        run("print(args);", &["a", "b"]),
+        (0, "[\"a\", \"b\"]\n".to_string())
+    );
+    assert_eq!(run("throw \"bad\";", &[]), (1, "bad\n".to_string()));
+    assert_eq!(run("exit(len(args)); print(1);", &["a", "b"]).0, 2);
+    assert_eq!(run("exit();", &[]).0, 0);
+}
+
+#[test]
+fn exit_is_not_caught_but_runs_finally() {
+    let source = "
+func stop() { exit(4); }
+try { stop(); } catch error { print(\"caught\"); } finally { print(\"finally\"); }
+print(\"after\");
+";
+    assert_eq!(run(source, &[]), (4, "finally\n".to_string()));
+}
+
+#[test]
+fn finds_the_paths_a_program_imports() {
+    let source = "
+import \"geometry.lt\" as geo;
+from \"util\" import clamp;
+func area() { return 1; }
+";
+    let statements = Parser::new(lexer(source)).parse().unwrap();
+    assert_eq!(imported_paths(&statements), vec!["geometry.lt", "util"]);
+}
+
+fn number(value: Option<Value>) -> Option<f64> {
+    match value {
+        Some(Value::NumberLiteral(number)) => Some(number),
+        _ => None,
+    }
+}
+
+// Waits for a blocked thread to reach its wait; the tests only rely on
+// this for timing, never for correctness.
+fn settle() {
+    thread::sleep(Duration::from_millis(50));
+}
+
+#[test]
+fn unbuffered_send_waits_for_a_receiver() {
+    let channel = Channel::new(0);
+    let (done, finished) = mpsc::channel();
+    let sender = {
+        let channel = channel.clone();
+        thread::spawn(move || {
+            channel.send(Value::NumberLiteral(1.0)).unwrap();
+            done.send(()).unwrap();
+        })
+    };
+
+    settle();
+    assert!(finished.try_recv().is_err());
+    assert_eq!(number(Some(channel.receive())), Some(1.0));
+    sender.join().unwrap();
+    assert!(finished.try_recv().is_ok());
+}
+
+#[test]
+fn buffered_send_blocks_only_when_full() {
+    let channel = Channel::new(2);
+    assert!(channel.try_send(Value::NumberLiteral(1.0)).unwrap());
+    assert!(channel.try_send(Value::NumberLiteral(2.0)).unwrap());
+    assert!(!channel.try_send(Value::NumberLiteral(3.0)).unwrap());
+    assert_eq!(channel.queued(), 2);
+
+    assert_eq!(number(channel.try_receive()), Some(1.0));
+    assert_eq!(number(channel.try_receive()), Some(2.0));
+    assert!(channel.try_receive().is_none());
+}
+
+#[test]
+fn unbuffered_try_send_needs_a_waiting_receiver() {
+    let channel = Channel::new(0);
+    assert!(!channel.try_send(Value::NumberLiteral(1.0)).unwrap());
+
+    let receiver = {
+        let channel = channel.clone();
+        thread::spawn(move || number(Some(channel.receive())))
+    };
+    settle();
+    assert!(channel.try_send(Value::NumberLiteral(2.0)).unwrap());
+    assert_eq!(receiver.join().unwrap(), Some(2.0));
+}
+
+#[test]
+fn unbuffered_try_send_does_not_hand_off_to_a_select() {
+    let (channel, other) = (Channel::new(0), Channel::new(0));
+    let selector = {
+        let channels = [channel.clone(), other.clone()];
+        thread::spawn(move || select(&channels, Some(Duration::from_millis(200))))
+    };
+    settle();
+    assert!(!channel.try_send(Value::NumberLiteral(3.0)).unwrap());
+    assert_eq!(channel.queued(), 0);
+    assert!(matches!(selector.join().unwrap(), Selected::TimedOut));
+}
+
+#[test]
+fn closing_drains_then_yields_nil_and_rejects_sends() {
+    let channel = Channel::new(1);
+    channel.send(Value::NumberLiteral(1.0)).unwrap();
+    channel.close();
+
+    assert!(channel.is_closed());
+    assert!(channel.send(Value::Nil).is_err());
+    assert!(channel.try_send(Value::Nil).is_err());
+    assert_eq!(number(Some(channel.receive())), Some(1.0));
+    assert!(matches!(channel.receive(), Value::Nil));
+    assert!(channel.try_receive().is_none());
+}
+
+#[test]
+fn closing_wakes_a_blocked_receiver_and_sender() {
+    let channel = Channel::new(0);
+    let receiver = {
+        let channel = channel.clone();
+        thread::spawn(move || channel.receive())
+    };
+    settle();
+    channel.close();
+    assert!(matches!(receiver.join().unwrap(), Value::Nil));
+
+    // The value was never received, so the send fails and takes it back.
+    let channel = Channel::new(0);
+    let sender = {
+        let channel = channel.clone();
+        thread::spawn(move || channel.send(Value::NumberLiteral(1.0)))
+    };
+    settle();
+    channel.close();
+    assert_eq!(
+        sender.join().unwrap(),
+        Err("Cannot send on a closed channel".to_string())
+    );
+    assert!(matches!(channel.receive(), Value::Nil));
+}
+
+#[test]
+fn select_times_out_when_nothing_is_ready() {
+    let channels = [Channel::new(1), Channel::new(1)];
+    let selected = select(&channels, Some(Duration::from_millis(20)));
+    assert!(matches!(selected, Selected::TimedOut));
+
+    channels[1].send(Value::NumberLiteral(4.0)).unwrap();
+    match select(&channels, Some(Duration::from_millis(20))) {
+        Selected::Received(index, value) => {
+            assert_eq!(index, 1);
+            assert_eq!(number(Some(value)), Some(4.0));
+        }
+        Selected::TimedOut => panic!("expected a value"),
+    }
+}
+
+#[test]
+fn lists_that_contain_themselves_print_and_compare() {
+    let source = "
+var a = [1];
+a.push(a);
+var b = [1];
+b.push(b);
+print(a);
+print([a, 2]);
+print(a == b);
+print(a == [1, [2]]);
+";
+    assert_eq!(
+        run(source, &[]),
+        (0, "[1, [...]]\n[[1, [...]], 2]\ntrue\nfalse\n".to_string())
+    );
+}
@@ -1,21 +1,32 @@
+use std::ops::Range;
+
 use crate::runtime::lexer::token::Token;
 use logos::Logos;
 
-#[allow(dead_code)]
+#[allow(dead_code, clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Logos)]
 enum LexerToken {
-    #[regex(r"[ \t\r\x0c]+", logos::skip)]
+    #[regex(r"[ \t\r\n\x0c]+")]
     Ignored,
 
-    #[regex(r"#.*", logos::skip)]
+    #[regex(r"#.*")]
     Comment,
 
-    #[regex(r"#\*[^*]*\*+(?:[^#*][^*]*\*+)*#\*#", logos::skip)]
+    #[regex(r"#\*[^*]*\*+(?:[^#*][^*]*\*+)*#\*#")]
     MultiLineComment,
 
     #[token("var")]
     Var,
 
+    #[token("let")]
+    Let,
+
+    #[token("mut")]
+    Mut,
+
+    #[token("const")]
+    Const,
+
     #[token("String")]
     StringType,
 
@@ -35,7 +46,7 @@ enum LexerToken {
     })]
     StringLiteral(String),
 
-    #[regex(r"-?\d+(\.\d+)?", |lex| lex.slice().parse::<f64>().unwrap())]
+    #[regex(r"\d+(\.\d+)?", |lex| lex.slice().parse::<f64>().unwrap())]
     NumberLiteral(f64),
 
     #[regex("true|false", |lex| lex.slice().parse::<bool>().unwrap())]
@@ -50,6 +61,15 @@ enum LexerToken {
     #[token(":")]
     Colon,
 
+    #[token(",")]
+    Comma,
+
+    #[token(".")]
+    Dot,
+
+    #[token("=>")]
+    FatArrow,
+
     #[token("print")]
     Print,
 
@@ -71,6 +91,24 @@ enum LexerToken {
     #[token("**")]
     Pow,
 
+    #[token("+=")]
+    PlusAssign,
+
+    #[token("-=")]
+    MinusAssign,
+
+    #[token("*=")]
+    StarAssign,
+
+    #[token("/=")]
+    SlashAssign,
+
+    #[token("%=")]
+    PercentAssign,
+
+    #[token("**=")]
+    PowAssign,
+
     #[token("&&")]
     And,
 
@@ -86,10 +124,9 @@ enum LexerToken {
     #[regex("or|OR")]
     OrWord,
 
-    /*
     #[token("not")]
     NotWord,
-    */
+
     #[token("==")]
     EqualEqual,
 
@@ -114,6 +151,15 @@ enum LexerToken {
     #[token("--")]
     Decrement,
 
+    #[token("|")]
+    Pipe,
+
+    #[token("?")]
+    Question,
+
+    #[token("??")]
+    Coalesce,
+
     #[token("(")]
     LeftParen,
 
@@ -126,6 +172,12 @@ enum LexerToken {
     #[token("}")]
     RightBrace,
 
+    #[token("[")]
+    LeftBracket,
+
+    #[token("]")]
+    RightBracket,
+
     #[token("if")]
     If,
 
@@ -138,80 +190,250 @@ enum LexerToken {
     #[token("while")]
     While,
 
-    #[end]
-    EOF,
-}
+    #[token("thread")]
+    Thread,
 
-pub fn lexer(input: &str) -> Vec<Token> {
-    let mut lexer = LexerToken::lexer(input);
-    let mut tokens = Vec::new();
+    #[token("test")]
+    Test,
 
-    while let Some(token) = lexer.next() {
-        match token {
-            Ok(LexerToken::Ignored) => continue,
-            Ok(LexerToken::Comment) => continue,
-            Ok(LexerToken::MultiLineComment) => continue,
+    #[token("select")]
+    Select,
+
+    #[token("as")]
+    As,
+
+    #[token("func")]
+    Func,
+
+    #[token("return")]
+    Return,
 
-            Ok(LexerToken::Var) => tokens.push(Token::Var),
-            Ok(LexerToken::Identifier(id)) => tokens.push(Token::Identifier(id)),
-            Ok(LexerToken::Assign) => tokens.push(Token::Assign),
+    #[token("struct")]
+    Struct,
 
-            Ok(LexerToken::StringType) => tokens.push(Token::StringType),
-            Ok(LexerToken::IntType) => tokens.push(Token::IntType),
-            Ok(LexerToken::BooleanType) => tokens.push(Token::BooleanType),
+    #[token("impl")]
+    Impl,
 
-            Ok(LexerToken::StringLiteral(lit)) => tokens.push(Token::StringLiteral(lit)),
-            Ok(LexerToken::NumberLiteral(num)) => tokens.push(Token::NumberLiteral(num)),
-            Ok(LexerToken::BooleanLiteral(b)) => tokens.push(Token::BooleanLiteral(b)),
+    #[token("class")]
+    Class,
 
-            Ok(LexerToken::Semicolon) => tokens.push(Token::Semicolon),
-            Ok(LexerToken::Colon) => tokens.push(Token::Colon),
+    #[token("extends")]
+    Extends,
 
-            Ok(LexerToken::Print) => tokens.push(Token::Print),
+    #[token("this")]
+    This,
 
-            Ok(LexerToken::If) => tokens.push(Token::If),
-            Ok(LexerToken::Else) => tokens.push(Token::Else),
-            Ok(LexerToken::Elif) => tokens.push(Token::Elif),
+    #[token("super")]
+    Super,
 
-            Ok(LexerToken::While) => tokens.push(Token::While),
+    #[token("instanceof")]
+    InstanceOf,
 
-            Ok(LexerToken::Plus) => tokens.push(Token::Plus),
-            Ok(LexerToken::Minus) => tokens.push(Token::Minus),
-            Ok(LexerToken::Star) => tokens.push(Token::Star),
-            Ok(LexerToken::Slash) => tokens.push(Token::Slash),
-            Ok(LexerToken::Percent) => tokens.push(Token::Percent),
-            Ok(LexerToken::Pow) => tokens.push(Token::Pow),
+    #[token("is")]
+    Is,
 
-            Ok(LexerToken::And) => tokens.push(Token::And),
-            Ok(LexerToken::Or) => tokens.push(Token::Or),
-            Ok(LexerToken::Not) => tokens.push(Token::Not),
+    #[token("nil")]
+    Nil,
 
-            Ok(LexerToken::AndWord) => tokens.push(Token::And),
-            Ok(LexerToken::OrWord) => tokens.push(Token::Or),
-            /*
-            Ok(LexerToken::NotWord) => tokens.push(Token::NotWord),
-            */
-            Ok(LexerToken::EqualEqual) => tokens.push(Token::EqualEqual),
-            Ok(LexerToken::NotEqual) => tokens.push(Token::NotEqual),
-            Ok(LexerToken::LessThan) => tokens.push(Token::LessThan),
-            Ok(LexerToken::LessThanEqual) => tokens.push(Token::LessThanEqual),
-            Ok(LexerToken::GreaterThan) => tokens.push(Token::GreaterThan),
-            Ok(LexerToken::GreaterThanEqual) => tokens.push(Token::GreaterThanEqual),
+    #[token("enum")]
+    Enum,
 
-            Ok(LexerToken::Increment) => tokens.push(Token::Increment),
-            Ok(LexerToken::Decrement) => tokens.push(Token::Decrement),
+    #[token("match")]
+    Match,
 
-            Ok(LexerToken::LeftParen) => tokens.push(Token::LeftParen),
-            Ok(LexerToken::RightParen) => tokens.push(Token::RightParen),
+    #[token("import")]
+    Import,
 
-            Ok(LexerToken::LeftBrace) => tokens.push(Token::LeftBrace),
-            Ok(LexerToken::RightBrace) => tokens.push(Token::RightBrace),
+    #[token("from")]
+    From,
 
-            Ok(LexerToken::EOF) => tokens.push(Token::EOF),
+    #[token("export")]
+    Export,
 
-            Err(_) => continue,
+    #[token("throw")]
+    Throw,
+
+    #[token("try")]
+    Try,
+
+    #[token("catch")]
+    Catch,
+
+    #[token("finally")]
+    Finally,
+
+    #[token("_")]
+    Underscore,
+
+    #[token("..")]
+    DotDot,
+
+    #[token("..=")]
+    DotDotEqual,
+
+    #[end]
+    EOF,
+}
+
+pub fn lexer(input: &str) -> Vec<Token> {
+    LexerToken::lexer(input)
+        .filter_map(|token| token.ok().and_then(convert))
+        .collect()
+}
+
+/// The same tokens as `lexer`, along with the line each one starts on.
+pub fn lexer_with_lines(input: &str) -> (Vec<Token>, Vec<usize>) {
+    let mut lexer = LexerToken::lexer(input);
+    let mut tokens = Vec::new();
+    let mut lines = Vec::new();
+    let (mut line, mut counted) = (1, 0);
+
+    while let Some(token) = lexer.next() {
+        if let Some(token) = token.ok().and_then(convert) {
+            let start = lexer.span().start;
+            line += input[counted..start].matches('\n').count();
+            counted = start;
+            tokens.push(token);
+            lines.push(line);
+        }
+    }
+
+    (tokens, lines)
+}
+
+/// Every token of `input` with its position, including comments and text
+/// the lexer does not recognize (as `None`). Only whitespace is left out.
+pub fn lossless(input: &str) -> Vec<(Option<Token>, Range<usize>)> {
+    let mut lexer = LexerToken::lexer(input);
+    let mut tokens = Vec::new();
+
+    while let Some(token) = lexer.next() {
+        let span = lexer.span();
+        match token {
+            Ok(LexerToken::Ignored) => {}
+            Ok(LexerToken::Comment | LexerToken::MultiLineComment) => {
+                tokens.push((Some(Token::Comment), span))
+            }
+            Ok(token) => tokens.push((convert(token), span)),
+            Err(_) => tokens.push((None, span)),
         }
     }
 
     tokens
 }
+
+fn convert(token: LexerToken) -> Option<Token> {
+    let token = match token {
+        LexerToken::Ignored => return None,
+        LexerToken::Comment => return None,
+        LexerToken::MultiLineComment => return None,
+
+        LexerToken::Var => Token::Var,
+        LexerToken::Let => Token::Let,
+        LexerToken::Mut => Token::Mut,
+        LexerToken::Const => Token::Const,
+        LexerToken::Identifier(id) => Token::Identifier(id),
+        LexerToken::Assign => Token::Assign,
+
+        LexerToken::StringType => Token::StringType,
+        LexerToken::IntType => Token::IntType,
+        LexerToken::BooleanType => Token::BooleanType,
+
+        LexerToken::StringLiteral(lit) => Token::StringLiteral(lit),
+        LexerToken::NumberLiteral(num) => Token::NumberLiteral(num),
+        LexerToken::BooleanLiteral(b) => Token::BooleanLiteral(b),
+
+        LexerToken::Semicolon => Token::Semicolon,
+        LexerToken::Colon => Token::Colon,
+        LexerToken::Comma => Token::Comma,
+        LexerToken::Dot => Token::Dot,
+        LexerToken::FatArrow => Token::FatArrow,
+
+        LexerToken::Print => Token::Print,
+
+        LexerToken::If => Token::If,
+        LexerToken::Else => Token::Else,
+        LexerToken::Elif => Token::Elif,
+
+        LexerToken::While => Token::While,
+
+        LexerToken::Thread => Token::Thread,
+        LexerToken::Test => Token::Test,
+        LexerToken::Select => Token::Select,
+        LexerToken::As => Token::As,
+
+        LexerToken::Func => Token::Func,
+        LexerToken::Return => Token::Return,
+
+        LexerToken::Struct => Token::Struct,
+        LexerToken::Impl => Token::Impl,
+
+        LexerToken::Class => Token::Class,
+        LexerToken::Extends => Token::Extends,
+        LexerToken::This => Token::This,
+        LexerToken::Super => Token::Super,
+
+        LexerToken::Enum => Token::Enum,
+        LexerToken::Match => Token::Match,
+        LexerToken::Import => Token::Import,
+        LexerToken::From => Token::From,
+        LexerToken::Export => Token::Export,
+        LexerToken::Throw => Token::Throw,
+        LexerToken::Try => Token::Try,
+        LexerToken::Catch => Token::Catch,
+        LexerToken::Finally => Token::Finally,
+        LexerToken::Underscore => Token::Underscore,
+        LexerToken::DotDot => Token::DotDot,
+        LexerToken::DotDotEqual => Token::DotDotEqual,
+
+        LexerToken::Plus => Token::Plus,
+        LexerToken::Minus => Token::Minus,
+        LexerToken::Star => Token::Star,
+        LexerToken::Slash => Token::Slash,
+        LexerToken::Percent => Token::Percent,
+        LexerToken::Pow => Token::Pow,
+        LexerToken::PlusAssign => Token::PlusAssign,
+        LexerToken::MinusAssign => Token::MinusAssign,
+        LexerToken::StarAssign => Token::StarAssign,
+        LexerToken::SlashAssign => Token::SlashAssign,
+        LexerToken::PercentAssign => Token::PercentAssign,
+        LexerToken::PowAssign => Token::PowAssign,
+
+        LexerToken::And => Token::And,
+        LexerToken::Or => Token::Or,
+        LexerToken::Not => Token::Not,
+
+        LexerToken::AndWord => Token::And,
+        LexerToken::OrWord => Token::Or,
+        LexerToken::NotWord => Token::Not,
+
+        LexerToken::EqualEqual => Token::EqualEqual,
+        LexerToken::NotEqual => Token::NotEqual,
+        LexerToken::LessThan => Token::LessThan,
+        LexerToken::LessThanEqual => Token::LessThanEqual,
+        LexerToken::GreaterThan => Token::GreaterThan,
+        LexerToken::GreaterThanEqual => Token::GreaterThanEqual,
+        LexerToken::InstanceOf => Token::InstanceOf,
+        LexerToken::Is => Token::Is,
+        LexerToken::Nil => Token::Nil,
+
+        LexerToken::Increment => Token::Increment,
+        LexerToken::Decrement => Token::Decrement,
+
+        LexerToken::Pipe => Token::Pipe,
+        LexerToken::Question => Token::Question,
+        LexerToken::Coalesce => Token::Coalesce,
+
+        LexerToken::LeftParen => Token::LeftParen,
+        LexerToken::RightParen => Token::RightParen,
+
+        LexerToken::LeftBrace => Token::LeftBrace,
+        LexerToken::RightBrace => Token::RightBrace,
+
+        LexerToken::LeftBracket => Token::LeftBracket,
+        LexerToken::RightBracket => Token::RightBracket,
+
+        LexerToken::EOF => Token::EOF,
+    };
+    Some(token)
+}
@@ -1,18 +1,28 @@
-#[derive(Debug, PartialEq, Clone)]
+use serde::Serialize;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, PartialEq, Clone, Serialize)]
 pub enum Token {
     Var,
+    Let,
+    Mut,
+    Const,
     Identifier(String),
     Assign,
 
     StringLiteral(String),
     NumberLiteral(f64),
     BooleanLiteral(bool),
+    Nil,
     StringType,
     IntType,
     BooleanType,
 
     Semicolon,
     Colon,
+    Comma,
+    Dot,
+    FatArrow,
 
     Print,
 
@@ -22,6 +32,38 @@ pub enum Token {
 
     While,
 
+    Thread,
+    Test,
+    Select,
+    As,
+
+    Func,
+    Return,
+
+    Struct,
+    Impl,
+
+    Class,
+    Extends,
+    This,
+    Super,
+
+    Enum,
+    Match,
+
+    Import,
+    From,
+    Export,
+
+    Throw,
+    Try,
+    Catch,
+    Finally,
+
+    Underscore,
+    DotDot,
+    DotDotEqual,
+
     Plus,
     Minus,
     Star,
@@ -29,6 +71,13 @@ pub enum Token {
     Percent,
     Pow,
 
+    PlusAssign,
+    MinusAssign,
+    StarAssign,
+    SlashAssign,
+    PercentAssign,
+    PowAssign,
+
     And,
     Or,
     Not,
@@ -39,15 +88,26 @@ pub enum Token {
     LessThanEqual,
     GreaterThan,
     GreaterThanEqual,
+    InstanceOf,
+    Is,
 
     Increment,
     Decrement,
 
+    Pipe,
+    Question,
+    Coalesce,
+
     LeftParen,
     RightParen,
 
     LeftBrace,
     RightBrace,
 
+    LeftBracket,
+    RightBracket,
+
+    Comment,
+
     EOF,
 }
@@ -1,31 +1,181 @@
+use serde::Serialize;
+
 use super::parser::Type;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum Statement {
     VariableDeclaration {
         name: String,
         value: Expression,
         value_type: Option<Type>,
+        binding: Binding,
     },
     VariableAssignment {
         name: String,
         value: Expression,
-        value_type: Option<Type>,
     },
+    PropertyAssignment {
+        object: Expression,
+        name: String,
+        value: Expression,
+    },
+    Expression(Expression),
     Print(Expression),
+    // `branch` indexes the parser's branch points, for coverage.
     If {
         condition: Expression,
         then_branch: Box<Statement>,
         else_branch: Option<Box<Statement>>,
+        branch: usize,
     },
     While {
         condition: Expression,
         body: Box<Statement>,
+        branch: usize,
     },
     Block(Vec<Statement>),
+    Function(FunctionDeclaration),
+    Return(Option<Expression>),
+    Struct {
+        name: String,
+        fields: Vec<Parameter>,
+    },
+    Impl {
+        name: String,
+        methods: Vec<FunctionDeclaration>,
+    },
+    Class {
+        name: String,
+        superclass: Option<String>,
+        methods: Vec<FunctionDeclaration>,
+    },
+    Enum {
+        name: String,
+        variants: Vec<Variant>,
+    },
+    Import {
+        path: String,
+        alias: Option<String>,
+    },
+    FromImport {
+        path: String,
+        names: Vec<String>,
+    },
+    Export(Box<Statement>),
+    ExportNames(Vec<String>),
+    Throw(Expression),
+    Try {
+        body: Vec<Statement>,
+        catch: Option<CatchClause>,
+        finally: Option<Vec<Statement>>,
+    },
+    Thread(Box<Statement>),
+    Select(Vec<SelectArm>),
+    // Skipped when a program runs; `luth test` runs each one on its own.
+    Test {
+        name: String,
+        body: Vec<Statement>,
+    },
+    // A statement with the line it starts on, for debugging and coverage.
+    Located {
+        line: usize,
+        statement: Box<Statement>,
+    },
+}
+
+/// How a variable was declared: `var` and `let mut` are mutable, `let` is
+/// immutable and `const` is a constant.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Binding {
+    Mutable,
+    Immutable,
+    Constant,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionDeclaration {
+    pub name: Option<String>,
+    // The line the declaration starts on, or 0 without line numbers.
+    pub line: usize,
+    pub parameters: Vec<Parameter>,
+    pub return_type: Option<Type>,
+    pub body: Vec<Statement>,
+}
+
+/// A place where execution takes one of several arms: an `if` with its
+/// `elif`s and `else`, or a loop that either runs its body or stops.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BranchPoint {
+    pub line: usize,
+    pub arms: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Parameter {
+    pub name: String,
+    pub value_type: Option<Type>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+pub struct Variant {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CatchClause {
+    pub name: Option<String>,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expression>,
+    pub body: Expression,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum Pattern {
+    Wildcard,
+    Binding(String),
+    Literal(Expression),
+    Range {
+        start: f64,
+        end: f64,
+        inclusive: bool,
+    },
+    Variant {
+        enum_name: Option<String>,
+        variant: String,
+        fields: Vec<Pattern>,
+    },
+    // `rest` is `Some(None)` for a bare `..` and `Some(Some(name))` for
+    // `..name`, which binds the remaining elements.
+    List {
+        elements: Vec<Pattern>,
+        rest: Option<Option<String>>,
+    },
+    Struct {
+        name: String,
+        fields: Vec<(String, Pattern)>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum SelectArm {
+    Receive {
+        channel: Expression,
+        binding: Option<String>,
+        body: Statement,
+    },
+    Timeout {
+        duration: Expression,
+        body: Statement,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub enum Expression {
     StringLiteral(String),
     NumberLiteral(f64),
@@ -41,6 +191,36 @@ pub enum Expression {
         operator: Operator,
         right: Box<Expression>,
     },
+    Call {
+        callee: Box<Expression>,
+        arguments: Vec<Expression>,
+    },
+    Get {
+        object: Box<Expression>,
+        name: String,
+    },
+    Index {
+        object: Box<Expression>,
+        index: Box<Expression>,
+    },
+    List(Vec<Expression>),
+    StructLiteral {
+        name: String,
+        fields: Vec<(String, Expression)>,
+    },
+    Function(FunctionDeclaration),
+    This,
+    Super(String),
+    Match {
+        value: Box<Expression>,
+        arms: Vec<MatchArm>,
+    },
+    Block(Vec<Statement>),
+    Is {
+        value: Box<Expression>,
+        value_type: Type,
+    },
+    Propagate(Box<Expression>),
     Increment(Box<Expression>),
     Decrement(Box<Expression>),
     InlineIf {
@@ -48,11 +228,12 @@ pub enum Expression {
         then_branch: Box<Expression>,
         elif_branches: Vec<(Box<Expression>, Box<Expression>)>,
         else_branch: Box<Expression>,
+        branch: usize,
     },
     Nil,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum Operator {
     Plus,
     Minus,
@@ -64,6 +245,7 @@ pub enum Operator {
     And,
     Or,
     Not,
+    Coalesce,
 
     EqualEqual,
     NotEqual,
@@ -71,6 +253,5 @@ pub enum Operator {
     LessThanEqual,
     GreaterThan,
     GreaterThanEqual,
-
-    Assign,
+    InstanceOf,
 }
@@ -1,2 +1,6 @@
 pub mod ast;
+#[allow(clippy::module_inception)]
 pub mod parser;
+pub mod symbols;
+#[cfg(test)]
+mod tests;
@@ -1,152 +1,1491 @@
+use std::collections::{BTreeSet, HashMap};
+
+use serde::Serialize;
+
+use crate::runtime::interpreter::module::module_name;
 use crate::runtime::lexer::token::Token;
-use crate::runtime::parser::ast::{Expression, Operator, Statement};
+use crate::runtime::parser::ast::{
+    Binding, BranchPoint, CatchClause, Expression, FunctionDeclaration, MatchArm, Operator,
+    Parameter, Pattern, SelectArm, Statement, Variant,
+};
+use crate::runtime::parser::symbols::{Declaration, Reference, SymbolKind, Symbols};
+
+pub struct Parser {
+    tokens: Vec<Token>,
+    // The line each token starts on, when known.
+    lines: Vec<usize>,
+    // The lines that start a statement.
+    statement_lines: BTreeSet<usize>,
+    branch_points: Vec<BranchPoint>,
+    current: usize,
+    function_depth: usize,
+    // One entry per class being parsed, telling whether it has a superclass.
+    classes: Vec<bool>,
+    struct_literals: bool,
+    enums: HashMap<String, Vec<String>>,
+    matches: Vec<MatchCoverage>,
+    warnings: Vec<String>,
+    // The enclosing scopes, innermost last, used to reject assignments to
+    // `const` and `let` bindings and to resolve names for `symbols`.
+    scopes: Vec<Scope>,
+    symbols: Symbols,
+    // Names that were not declared yet where they were used, as a function
+    // may call another declared after it. Resolved once parsing is done.
+    unresolved: Vec<(usize, String)>,
+}
+
+// Binary operators from loosest to tightest binding:
+//
+//   1  ??                      right
+//   2  or ||                   left
+//   3  and &&                  left
+//   4  == !=                   left
+//   5  < <= > >= instanceof is left
+//   6  + -                     left
+//   7  * / %                   left
+//   8  **                      right
+//
+// Prefix `!`, `not` and `-` sit between levels 7 and 8: their operand may
+// contain `**`, so `-2 ** 2` is `-(2 ** 2)`, while `-a * b` is `(-a) * b`.
+const LOWEST_PRECEDENCE: u8 = 1;
+const PREFIX_OPERAND: u8 = 8;
+
+// The precedence of an infix operator and whether it is right-associative.
+fn infix_precedence(token: &Token) -> Option<(u8, bool)> {
+    let precedence = match token {
+        Token::Coalesce => (1, true),
+        Token::Or => (2, false),
+        Token::And => (3, false),
+        Token::EqualEqual | Token::NotEqual => (4, false),
+        Token::LessThan
+        | Token::LessThanEqual
+        | Token::GreaterThan
+        | Token::GreaterThanEqual
+        | Token::InstanceOf
+        | Token::Is => (5, false),
+        Token::Plus | Token::Minus => (6, false),
+        Token::Star | Token::Slash | Token::Percent => (7, false),
+        Token::Pow => (8, true),
+        _ => return None,
+    };
+    Some(precedence)
+}
+
+struct Scope {
+    id: usize,
+    // Each name's binding and its index in `symbols.declarations`.
+    names: HashMap<String, (Binding, usize)>,
+}
+
+// A name a new scope starts with, such as a parameter, with its kind and
+// description for `symbols`.
+type Local = (String, SymbolKind, String);
+
+// The enum variants a `match` handles, checked once the whole program is
+// parsed so enums declared after the `match` are known too.
+struct MatchCoverage {
+    enum_name: Option<String>,
+    variants: Vec<String>,
+    covered: Vec<String>,
+    catch_all: bool,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        let program = 0..tokens.len();
+        Parser {
+            tokens,
+            lines: Vec::new(),
+            statement_lines: BTreeSet::new(),
+            branch_points: Vec::new(),
+            current: 0,
+            function_depth: 0,
+            classes: Vec::new(),
+            struct_literals: true,
+            enums: HashMap::from([
+                (
+                    "Result".to_string(),
+                    vec!["Ok".to_string(), "Err".to_string()],
+                ),
+                (
+                    "Option".to_string(),
+                    vec!["Some".to_string(), "None".to_string()],
+                ),
+            ]),
+            matches: Vec::new(),
+            warnings: Vec::new(),
+            scopes: vec![Scope {
+                id: 0,
+                names: HashMap::new(),
+            }],
+            symbols: Symbols {
+                scopes: vec![program],
+                ..Symbols::default()
+            },
+            unresolved: Vec::new(),
+        }
+    }
+
+    /// A parser that records the line of each statement it reads, given
+    /// the line of each token as returned by `lexer_with_lines`.
+    pub fn with_lines(tokens: Vec<Token>, lines: Vec<usize>) -> Self {
+        Parser {
+            lines,
+            ..Parser::new(tokens)
+        }
+    }
+
+    pub fn parse(&mut self) -> Result<Vec<Statement>, String> {
+        let mut statements = Vec::new();
+
+        while !self.is_at_end() {
+            if self.peek() == Token::Export {
+                statements.push(self.located(Self::export_statement)?);
+            } else if self.peek() == Token::Test {
+                statements.push(self.located(Self::test_statement)?);
+            } else {
+                statements.push(self.statement()?);
+            }
+        }
+        self.check_match_exhaustiveness();
+        self.resolve_forward_references();
+
+        Ok(statements)
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// The lines a statement starts on, where execution can stop. Empty
+    /// unless the parser was built `with_lines`.
+    pub fn statement_lines(&self) -> &BTreeSet<usize> {
+        &self.statement_lines
+    }
+
+    /// The `if`s and loops read so far, indexed by their `branch` field.
+    pub fn branch_points(&self) -> &[BranchPoint] {
+        &self.branch_points
+    }
+
+    pub fn into_symbols(self) -> Symbols {
+        self.symbols
+    }
+
+    /// The index of the token the parser stopped at, which is where the
+    /// problem is when `parse` fails.
+    pub fn position(&self) -> usize {
+        self.current
+    }
+
+    fn resolve_forward_references(&mut self) {
+        for (token, name) in std::mem::take(&mut self.unresolved) {
+            let declaration = self
+                .symbols
+                .declarations
+                .iter()
+                .position(|declaration| declaration.scope == 0 && declaration.name == name);
+            if let Some(declaration) = declaration {
+                self.symbols
+                    .references
+                    .push(Reference { token, declaration });
+            }
+        }
+        self.symbols
+            .references
+            .sort_by_key(|reference| reference.token);
+    }
+
+    fn check_match_exhaustiveness(&mut self) {
+        for coverage in &self.matches {
+            if coverage.catch_all || coverage.variants.is_empty() {
+                continue;
+            }
+
+            let known = match &coverage.enum_name {
+                Some(name) => self.enums.get_key_value(name),
+                None => {
+                    let mut candidates = self.enums.iter().filter(|(_, variants)| {
+                        coverage.variants.iter().all(|used| variants.contains(used))
+                    });
+                    match (candidates.next(), candidates.next()) {
+                        (Some(candidate), None) => Some(candidate),
+                        _ => None,
+                    }
+                }
+            };
+
+            if let Some((name, variants)) = known {
+                let missing: Vec<&str> = variants
+                    .iter()
+                    .filter(|variant| !coverage.covered.contains(variant))
+                    .map(String::as_str)
+                    .collect();
+                if !missing.is_empty() {
+                    self.warnings.push(format!(
+                        "Non-exhaustive match on '{}': missing {}",
+                        name,
+                        missing.join(", ")
+                    ));
+                }
+            }
+        }
+    }
+
+    fn statement(&mut self) -> Result<Statement, String> {
+        self.located(Self::statement_kind)
+    }
+
+    fn located(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<Statement, String>,
+    ) -> Result<Statement, String> {
+        let line = self.line();
+        if line > 0 {
+            self.statement_lines.insert(line);
+        }
+        Ok(Statement::Located {
+            line,
+            statement: Box::new(parse(self)?),
+        })
+    }
+
+    fn statement_kind(&mut self) -> Result<Statement, String> {
+        match self.peek() {
+            Token::Var | Token::Let | Token::Const => self.variable_declaration(),
+            Token::Identifier(_)
+                if matches!(
+                    self.peek_next(),
+                    Token::Assign
+                        | Token::Increment
+                        | Token::Decrement
+                        | Token::PlusAssign
+                        | Token::MinusAssign
+                        | Token::StarAssign
+                        | Token::SlashAssign
+                        | Token::PercentAssign
+                        | Token::PowAssign
+                ) =>
+            {
+                self.variable_assignment()
+            }
+            Token::Print => self.print_statement(),
+            Token::If => self.if_statement(),
+            Token::While => self.while_statement(),
+            Token::Func if matches!(self.peek_next(), Token::Identifier(_)) => {
+                self.function_declaration()
+            }
+            Token::Return => self.return_statement(),
+            Token::Struct => self.struct_declaration(),
+            Token::Impl => self.impl_declaration(),
+            Token::Class => self.class_declaration(),
+            Token::Enum => self.enum_declaration(),
+            Token::Import => self.import_statement(),
+            Token::From => self.selective_import_statement(),
+            Token::Export => Err("'export' is only allowed at the top level".to_string()),
+            Token::Test => Err("'test' is only allowed at the top level".to_string()),
+            Token::Match => {
+                let expr = self.match_expression()?;
+                if self.peek() == Token::Semicolon {
+                    self.advance();
+                }
+                Ok(Statement::Expression(expr))
+            }
+            Token::Throw => self.throw_statement(),
+            Token::Try => self.try_statement(),
+            Token::Thread => self.thread_statement(),
+            Token::Select => self.select_statement(),
+            Token::EOF => Err("Unexpected end of input".to_string()),
+            _ => self.expression_statement(),
+        }
+    }
+
+    fn expression_statement(&mut self) -> Result<Statement, String> {
+        let expr = self.expression()?;
+
+        if matches!(
+            self.peek(),
+            Token::Assign
+                | Token::PlusAssign
+                | Token::MinusAssign
+                | Token::StarAssign
+                | Token::SlashAssign
+                | Token::PercentAssign
+                | Token::PowAssign
+        ) {
+            let token = self.advance();
+            let (object, name) = match &expr {
+                Expression::Get { object, name } => (*object.clone(), name.clone()),
+                _ => return Err("Invalid assignment target".to_string()),
+            };
+            let value = self.assigned_value(token, expr)?;
+            if self.peek() != Token::Semicolon {
+                return Err("Expected ';' after assignment".to_string());
+            }
+            self.advance();
+            return Ok(Statement::PropertyAssignment {
+                object,
+                name,
+                value,
+            });
+        }
+
+        if self.peek() == Token::Semicolon {
+            self.advance();
+            return Ok(Statement::Expression(expr));
+        }
+        Err("Expected ';' after expression".to_string())
+    }
+
+    fn variable_declaration(&mut self) -> Result<Statement, String> {
+        let binding = match self.advance() {
+            Token::Const => Binding::Constant,
+            Token::Let if self.peek() == Token::Mut => {
+                self.advance();
+                Binding::Mutable
+            }
+            Token::Let => Binding::Immutable,
+            _ => Binding::Mutable,
+        };
+        let name = match self.advance() {
+            Token::Identifier(name) => name,
+            _ => return Err("Expected variable name".to_string()),
+        };
+        let token = self.current - 1;
+        self.check_redeclarable(&name)?;
+
+        let mut value_type = None;
+        if let Token::Colon = self.peek() {
+            self.advance();
+            value_type = Some(self.parse_type()?);
+        }
+
+        if let Token::Assign = self.advance() {
+            let value = self.expression()?;
+            if let Token::Semicolon = self.advance() {
+                let (kind, keyword) = match binding {
+                    Binding::Constant => (SymbolKind::Constant, "const"),
+                    Binding::Immutable => (SymbolKind::Variable, "let"),
+                    Binding::Mutable if self.tokens[token - 1] == Token::Mut => {
+                        (SymbolKind::Variable, "let mut")
+                    }
+                    Binding::Mutable => (SymbolKind::Variable, "var"),
+                };
+                let detail = match value_type.as_ref().map(Type::to_string) {
+                    Some(value_type) => format!("{} {}: {}", keyword, name, value_type),
+                    None => match self.infer_type(&value) {
+                        Some(inferred) => format!("{} {}: {}", keyword, name, inferred),
+                        None => format!("{} {}", keyword, name),
+                    },
+                };
+                let declaration = self.record(kind, &name, token, detail);
+                self.declare(name.clone(), binding, declaration);
+                return Ok(Statement::VariableDeclaration {
+                    name,
+                    value,
+                    value_type,
+                    binding,
+                });
+            }
+        }
+        Err("Invalid variable declaration".to_string())
+    }
+
+    fn variable_assignment(&mut self) -> Result<Statement, String> {
+        let name = match self.advance() {
+            Token::Identifier(name) => name,
+            _ => return Err("Expected variable name".to_string()),
+        };
+        self.check_assignable(&name)?;
+        self.reference(&name, self.current - 1);
+
+        let target = Expression::Identifier(name.clone());
+        let expr = match self.advance() {
+            Token::Increment => Expression::Increment(Box::new(target)),
+            Token::Decrement => Expression::Decrement(Box::new(target)),
+            token => self.assigned_value(token, target)?,
+        };
+
+        if let Token::Semicolon = self.advance() {
+            return Ok(Statement::VariableAssignment { name, value: expr });
+        }
+        Err("Invalid variable assignment".to_string())
+    }
+
+    // The value stored by `=` or a compound assignment such as `+=`, which
+    // becomes `target + value`.
+    fn assigned_value(&mut self, token: Token, target: Expression) -> Result<Expression, String> {
+        let operator = match token {
+            Token::Assign => return self.expression(),
+            Token::PlusAssign => Operator::Plus,
+            Token::MinusAssign => Operator::Minus,
+            Token::StarAssign => Operator::Star,
+            Token::SlashAssign => Operator::Slash,
+            Token::PercentAssign => Operator::Percent,
+            Token::PowAssign => Operator::Pow,
+            _ => return Err("Expected assignment operator".to_string()),
+        };
+
+        Ok(Expression::Binary {
+            left: Box::new(target),
+            operator,
+            right: Box::new(self.expression()?),
+        })
+    }
+
+    fn print_statement(&mut self) -> Result<Statement, String> {
+        self.advance();
+        if self.peek() == Token::LeftParen {
+            self.advance();
+            let value = self.expression()?;
+            if self.peek() == Token::RightParen {
+                self.advance();
+                if self.peek() == Token::Semicolon {
+                    self.advance();
+                    return Ok(Statement::Print(value));
+                }
+            }
+        }
+        Err("Invalid print statement".to_string())
+    }
+
+    // The line of the current token, or 0 without line numbers.
+    fn line(&self) -> usize {
+        self.lines.get(self.current).copied().unwrap_or_default()
+    }
+
+    // Records a branch point starting at the current token.
+    fn branch_point(&mut self, arms: usize) -> usize {
+        let line = self.line();
+        self.branch_points.push(BranchPoint { line, arms });
+        self.branch_points.len() - 1
+    }
+
+    fn if_statement(&mut self) -> Result<Statement, String> {
+        let branch = self.branch_point(2);
+        self.advance();
+
+        let condition = self.condition()?;
+        let then_branch = Box::new(self.block()?);
+
+        let else_branch = if self.peek() == Token::Else {
+            self.advance();
+            Some(Box::new(self.block()?))
+        } else {
+            None
+        };
+
+        Ok(Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+            branch,
+        })
+    }
+
+    fn while_statement(&mut self) -> Result<Statement, String> {
+        let branch = self.branch_point(2);
+        self.advance();
+
+        let condition = self.condition()?;
+        let body = Box::new(self.block()?);
+
+        Ok(Statement::While {
+            condition,
+            body,
+            branch,
+        })
+    }
+
+    fn function_declaration(&mut self) -> Result<Statement, String> {
+        self.advance();
+        let name = match self.advance() {
+            Token::Identifier(name) => name,
+            _ => return Err("Expected function name".to_string()),
+        };
+        let declaration = self.record(SymbolKind::Function, &name, self.current - 1, String::new());
+        self.declare(name.clone(), Binding::Mutable, declaration);
+
+        let function = self.function(Some(name))?;
+        self.symbols.declarations[declaration].detail = signature(&function);
+        Ok(Statement::Function(function))
+    }
+
+    // Adds a declaration to `symbols` in the current scope, returning its
+    // index.
+    fn record(&mut self, kind: SymbolKind, name: &str, token: usize, detail: String) -> usize {
+        self.symbols.declarations.push(Declaration {
+            name: name.to_string(),
+            kind,
+            token,
+            scope: self.scopes.last().map_or(0, |scope| scope.id),
+            detail,
+            container: None,
+        });
+        self.symbols.declarations.len() - 1
+    }
+
+    fn declare(&mut self, name: String, binding: Binding, declaration: usize) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.names.insert(name, (binding, declaration));
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<(Binding, usize)> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.names.get(name).copied())
+    }
+
+    fn reference(&mut self, name: &str, token: usize) {
+        match self.lookup(name) {
+            Some((_, declaration)) => self
+                .symbols
+                .references
+                .push(Reference { token, declaration }),
+            None => self.unresolved.push((token, name.to_string())),
+        }
+    }
+
+    fn check_assignable(&self, name: &str) -> Result<(), String> {
+        match self.lookup(name) {
+            Some((Binding::Constant, _)) => Err(format!("Cannot assign to constant '{}'", name)),
+            Some((Binding::Immutable, _)) => {
+                Err(format!("Cannot assign to immutable variable '{}'", name))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Constants and immutable variables cannot be shadowed by another
+    // declaration in the same scope, which would make them reassignable.
+    fn check_redeclarable(&self, name: &str) -> Result<(), String> {
+        let scope = self.scopes.last().and_then(|scope| scope.names.get(name));
+        match scope {
+            Some((Binding::Constant, _)) => Err(format!("Cannot redeclare constant '{}'", name)),
+            Some((Binding::Immutable, _)) => {
+                Err(format!("Cannot redeclare immutable variable '{}'", name))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Parses `f` in a new scope where `locals` are declared as mutable.
+    fn scoped<T>(
+        &mut self,
+        locals: impl IntoIterator<Item = Local>,
+        f: impl FnOnce(&mut Self) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let id = self.symbols.scopes.len();
+        self.symbols.scopes.push(self.current..self.current);
+        self.scopes.push(Scope {
+            id,
+            names: HashMap::new(),
+        });
+
+        for (name, kind, detail) in locals {
+            let token = self.token_of(&name);
+            let declaration = self.record(kind, &name, token, detail);
+            self.declare(name, Binding::Mutable, declaration);
+        }
+        let result = f(self);
+
+        self.scopes.pop();
+        self.symbols.scopes[id].end = self.current;
+        result
+    }
+
+    // The most recent token naming `name`, where a parameter or pattern
+    // binding that is about to be declared was written.
+    fn token_of(&self, name: &str) -> usize {
+        self.tokens[..self.current.min(self.tokens.len())]
+            .iter()
+            .rposition(|token| matches!(token, Token::Identifier(id) if id == name))
+            .unwrap_or(self.current)
+    }
+
+    // The type of a declaration's initial value, where it is obvious.
+    fn infer_type(&self, expression: &Expression) -> Option<String> {
+        let inferred = match expression {
+            Expression::StringLiteral(_) => "String",
+            Expression::NumberLiteral(_) => "Int",
+            Expression::BooleanLiteral(_) | Expression::Is { .. } => "Bool",
+            Expression::List(_) => "List",
+            Expression::Function(_) => "Function",
+            Expression::Nil => "Nil",
+            Expression::StructLiteral { name, .. } => return Some(name.clone()),
+            Expression::Unary { operator, right } => match operator {
+                Operator::Not => "Bool",
+                _ => return self.infer_type(right),
+            },
+            Expression::Binary {
+                left,
+                operator,
+                right,
+            } => match operator {
+                Operator::EqualEqual
+                | Operator::NotEqual
+                | Operator::LessThan
+                | Operator::LessThanEqual
+                | Operator::GreaterThan
+                | Operator::GreaterThanEqual
+                | Operator::InstanceOf => "Bool",
+                Operator::And | Operator::Or | Operator::Coalesce | Operator::Not => return None,
+                _ => {
+                    let left = self.infer_type(left)?;
+                    let right = self.infer_type(right)?;
+                    return (left == right).then_some(left);
+                }
+            },
+            Expression::Call { callee, .. } => match callee.as_ref() {
+                Expression::Identifier(name) => {
+                    let (_, declaration) = self.lookup(name)?;
+                    let declaration = &self.symbols.declarations[declaration];
+                    return (declaration.kind == SymbolKind::Class).then(|| name.clone());
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(inferred.to_string())
+    }
+
+    fn function(&mut self, name: Option<String>) -> Result<FunctionDeclaration, String> {
+        let line = self.line();
+        if self.peek() != Token::LeftParen {
+            return Err("Expected '(' after func".to_string());
+        }
+        self.advance();
+        let parameters = self.parameters(Token::RightParen)?;
+
+        let mut return_type = None;
+        if self.peek() == Token::Colon {
+            self.advance();
+            return_type = Some(self.parse_type()?);
+        }
+
+        let locals = parameter_locals(&parameters);
+        let body = self.scoped(locals, Self::function_body)?;
+
+        Ok(FunctionDeclaration {
+            name,
+            line,
+            parameters,
+            return_type,
+            body,
+        })
+    }
+
+    fn lambda(&mut self) -> Result<Expression, String> {
+        let line = self.line();
+        let parameters = match self.advance() {
+            Token::Or => Vec::new(),
+            _ => self.parameters(Token::Pipe)?,
+        };
+
+        let body = self.scoped(parameter_locals(&parameters), |parser| {
+            if parser.peek() == Token::LeftBrace {
+                parser.function_body()
+            } else {
+                parser.function_depth += 1;
+                let value = parser.expression();
+                parser.function_depth -= 1;
+                Ok(vec![Statement::Return(Some(value?))])
+            }
+        })?;
+
+        Ok(Expression::Function(FunctionDeclaration {
+            name: None,
+            line,
+            parameters,
+            return_type: None,
+            body,
+        }))
+    }
+
+    fn parameters(&mut self, closing: Token) -> Result<Vec<Parameter>, String> {
+        let mut parameters = Vec::new();
+
+        while self.peek() != closing {
+            let name = match self.advance() {
+                Token::Identifier(name) => name,
+                _ => return Err("Expected parameter name".to_string()),
+            };
+
+            let mut value_type = None;
+            if self.peek() == Token::Colon {
+                self.advance();
+                value_type = Some(self.parse_type()?);
+            }
+            parameters.push(Parameter { name, value_type });
+
+            if self.peek() != Token::Comma {
+                break;
+            }
+            self.advance();
+        }
+
+        if self.peek() != closing {
+            return Err("Expected end of parameter list".to_string());
+        }
+        self.advance();
+
+        Ok(parameters)
+    }
+
+    fn function_body(&mut self) -> Result<Vec<Statement>, String> {
+        self.function_depth += 1;
+        let body = self.block_statements();
+        self.function_depth -= 1;
+
+        body
+    }
+
+    fn return_statement(&mut self) -> Result<Statement, String> {
+        self.advance();
+
+        if self.function_depth == 0 {
+            return Err("Cannot return from top-level code".to_string());
+        }
+
+        let value = if self.peek() == Token::Semicolon {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+
+        if self.peek() != Token::Semicolon {
+            return Err("Expected ';' after return value".to_string());
+        }
+        self.advance();
+
+        Ok(Statement::Return(value))
+    }
+
+    fn struct_declaration(&mut self) -> Result<Statement, String> {
+        self.advance();
+        let name = match self.advance() {
+            Token::Identifier(name) => name,
+            _ => return Err("Expected struct name".to_string()),
+        };
+        let token = self.current - 1;
+
+        if self.peek() != Token::LeftBrace {
+            return Err("Expected '{' after struct name".to_string());
+        }
+        self.advance();
+        let fields = self.parameters(Token::RightBrace)?;
+
+        let detail = format!("struct {} {{ {} }}", name, parameter_list(&fields));
+        let declaration = self.record(SymbolKind::Struct, &name, token, detail);
+        self.declare(name.clone(), Binding::Mutable, declaration);
+        Ok(Statement::Struct { name, fields })
+    }
+
+    fn impl_declaration(&mut self) -> Result<Statement, String> {
+        self.advance();
+        let name = match self.advance() {
+            Token::Identifier(name) => name,
+            _ => return Err("Expected struct name after impl".to_string()),
+        };
+        self.reference(&name, self.current - 1);
+
+        if self.peek() != Token::LeftBrace {
+            return Err("Expected '{' after impl name".to_string());
+        }
+        self.advance();
+
+        let mut methods = Vec::new();
+        while self.peek() == Token::Func {
+            self.advance();
+            let method = match self.advance() {
+                Token::Identifier(method) => method,
+                _ => return Err("Expected method name".to_string()),
+            };
+            methods.push(self.method(method, &name)?);
+        }
+
+        if self.peek() != Token::RightBrace {
+            return Err("Expected '}' to end impl".to_string());
+        }
+        self.advance();
+
+        Ok(Statement::Impl { name, methods })
+    }
+
+    fn class_declaration(&mut self) -> Result<Statement, String> {
+        self.advance();
+        let name = match self.advance() {
+            Token::Identifier(name) => name,
+            _ => return Err("Expected class name".to_string()),
+        };
+        let token = self.current - 1;
+
+        let mut superclass = None;
+        if self.peek() == Token::Extends {
+            self.advance();
+            superclass = match self.advance() {
+                Token::Identifier(superclass) if superclass == name => {
+                    return Err("A class cannot extend itself".to_string())
+                }
+                Token::Identifier(superclass) => Some(superclass),
+                _ => return Err("Expected superclass name after extends".to_string()),
+            };
+            if let Some(superclass) = &superclass {
+                self.reference(superclass, self.current - 1);
+            }
+        }
+
+        let detail = match &superclass {
+            Some(superclass) => format!("class {} extends {}", name, superclass),
+            None => format!("class {}", name),
+        };
+        let declaration = self.record(SymbolKind::Class, &name, token, detail);
+        self.declare(name.clone(), Binding::Mutable, declaration);
+
+        if self.peek() != Token::LeftBrace {
+            return Err("Expected '{' before class body".to_string());
+        }
+        self.advance();
+
+        self.classes.push(superclass.is_some());
+        let methods = self.class_methods(&name);
+        self.classes.pop();
+        let methods = methods?;
+
+        if self.peek() != Token::RightBrace {
+            return Err("Expected '}' after class body".to_string());
+        }
+        self.advance();
+
+        Ok(Statement::Class {
+            name,
+            superclass,
+            methods,
+        })
+    }
+
+    fn class_methods(&mut self, class: &str) -> Result<Vec<FunctionDeclaration>, String> {
+        let mut methods = Vec::new();
+
+        while !matches!(self.peek(), Token::RightBrace | Token::EOF) {
+            if self.peek() == Token::Func {
+                self.advance();
+            }
+            let method = match self.advance() {
+                Token::Identifier(method) => method,
+                _ => return Err("Expected method name".to_string()),
+            };
+            methods.push(self.method(method, class)?);
+        }
+
+        Ok(methods)
+    }
+
+    // Parses a method of a struct or class once its name has been read.
+    fn method(&mut self, name: String, container: &str) -> Result<FunctionDeclaration, String> {
+        let declaration = self.record(SymbolKind::Method, &name, self.current - 1, String::new());
+        self.symbols.declarations[declaration].container = Some(container.to_string());
+
+        let method = self.function(Some(name))?;
+        self.symbols.declarations[declaration].detail = signature(&method);
+        Ok(method)
+    }
+
+    fn enum_declaration(&mut self) -> Result<Statement, String> {
+        self.advance();
+        let name = match self.advance() {
+            Token::Identifier(name) => name,
+            _ => return Err("Expected enum name".to_string()),
+        };
+        let token = self.current - 1;
+
+        if self.peek() != Token::LeftBrace {
+            return Err("Expected '{' after enum name".to_string());
+        }
+        self.advance();
+
+        let mut variants: Vec<Variant> = Vec::new();
+        while self.peek() != Token::RightBrace {
+            let variant = match self.advance() {
+                Token::Identifier(variant) => variant,
+                _ => return Err("Expected variant name".to_string()),
+            };
+            if variants.iter().any(|existing| existing.name == variant) {
+                return Err(format!(
+                    "Duplicate variant '{}' in enum '{}'",
+                    variant, name
+                ));
+            }
+
+            let mut fields = Vec::new();
+            if self.peek() == Token::LeftParen {
+                self.advance();
+                fields = self
+                    .parameters(Token::RightParen)?
+                    .into_iter()
+                    .map(|field| field.name)
+                    .collect();
+            }
+            variants.push(Variant {
+                name: variant,
+                fields,
+            });
+
+            if self.peek() != Token::Comma {
+                break;
+            }
+            self.advance();
+        }
+
+        if self.peek() != Token::RightBrace {
+            return Err("Expected '}' to end enum".to_string());
+        }
+        self.advance();
+
+        let names: Vec<String> = variants
+            .iter()
+            .map(|variant| variant.name.clone())
+            .collect();
+        let detail = format!("enum {} {{ {} }}", name, names.join(", "));
+        let declaration = self.record(SymbolKind::Enum, &name, token, detail);
+        self.declare(name.clone(), Binding::Mutable, declaration);
+
+        self.enums.insert(name.clone(), names);
+        Ok(Statement::Enum { name, variants })
+    }
+
+    fn match_expression(&mut self) -> Result<Expression, String> {
+        self.advance();
+        let value = Box::new(self.condition()?);
+
+        if self.peek() != Token::LeftBrace {
+            return Err("Expected '{' after match value".to_string());
+        }
+        self.advance();
+
+        let mut arms = Vec::new();
+        while !matches!(self.peek(), Token::RightBrace | Token::EOF) {
+            let arm = self.match_arm()?;
+            let is_block = matches!(arm.body, Expression::Block(_));
+            arms.push(arm);
+
+            if self.peek() == Token::Comma {
+                self.advance();
+            } else if !is_block {
+                break;
+            }
+        }
+
+        if self.peek() != Token::RightBrace {
+            return Err("Expected '}' to end match".to_string());
+        }
+        self.advance();
+
+        self.record_match_coverage(&arms);
+        Ok(Expression::Match { value, arms })
+    }
+
+    fn match_arm(&mut self) -> Result<MatchArm, String> {
+        let pattern = self.pattern()?;
+        let mut names = Vec::new();
+        pattern_bindings(&pattern, &mut names);
+        let locals = names
+            .into_iter()
+            .map(|name| (name.clone(), SymbolKind::Variable, name));
+        self.scoped(locals, |parser| parser.match_arm_body(pattern))
+    }
+
+    fn match_arm_body(&mut self, pattern: Pattern) -> Result<MatchArm, String> {
+        let mut guard = None;
+        if self.peek() == Token::If {
+            self.advance();
+            guard = Some(self.expression()?);
+        }
+
+        if self.peek() != Token::FatArrow {
+            return Err("Expected '=>' after match pattern".to_string());
+        }
+        self.advance();
+
+        let body = if self.peek() == Token::LeftBrace {
+            Expression::Block(self.block_statements()?)
+        } else {
+            self.expression()?
+        };
+
+        Ok(MatchArm {
+            pattern,
+            guard,
+            body,
+        })
+    }
+
+    fn record_match_coverage(&mut self, arms: &[MatchArm]) {
+        let mut coverage = MatchCoverage {
+            enum_name: None,
+            variants: Vec::new(),
+            covered: Vec::new(),
+            catch_all: false,
+        };
+
+        for arm in arms {
+            match &arm.pattern {
+                Pattern::Wildcard | Pattern::Binding(_) if arm.guard.is_none() => {
+                    coverage.catch_all = true;
+                }
+                Pattern::Variant {
+                    enum_name,
+                    variant,
+                    fields,
+                } => {
+                    if coverage.enum_name.is_none() {
+                        coverage.enum_name = enum_name.clone();
+                    }
+                    coverage.variants.push(variant.clone());
+
+                    let irrefutable = fields
+                        .iter()
+                        .all(|field| matches!(field, Pattern::Wildcard | Pattern::Binding(_)));
+                    if arm.guard.is_none() && irrefutable {
+                        coverage.covered.push(variant.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.matches.push(coverage);
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, String> {
+        if matches!(self.peek(), Token::NumberLiteral(_) | Token::Minus) {
+            return self.number_pattern();
+        }
+
+        match self.advance() {
+            Token::Underscore => Ok(Pattern::Wildcard),
+            Token::StringLiteral(lit) => Ok(Pattern::Literal(Expression::StringLiteral(lit))),
+            Token::BooleanLiteral(b) => Ok(Pattern::Literal(Expression::BooleanLiteral(b))),
+            Token::Nil => Ok(Pattern::Literal(Expression::Nil)),
+            Token::LeftBracket => self.list_pattern(),
+            Token::Identifier(name) => match self.peek() {
+                Token::Dot => {
+                    self.reference(&name, self.current - 1);
+                    self.advance();
+                    let variant = match self.advance() {
+                        Token::Identifier(variant) => variant,
+                        _ => return Err("Expected variant name after '.'".to_string()),
+                    };
+                    self.variant_pattern(Some(name), variant)
+                }
+                Token::LeftParen => self.variant_pattern(None, name),
+                Token::LeftBrace => {
+                    self.reference(&name, self.current - 1);
+                    self.struct_pattern(name)
+                }
+                // A bare name is a unit variant when a declared enum has it.
+                _ if self.enums.values().any(|variants| variants.contains(&name)) => {
+                    self.variant_pattern(None, name)
+                }
+                _ => Ok(Pattern::Binding(name)),
+            },
+            _ => Err("Unexpected token in pattern".to_string()),
+        }
+    }
+
+    fn number_pattern(&mut self) -> Result<Pattern, String> {
+        let start = self.pattern_number()?;
+        let inclusive = match self.peek() {
+            Token::DotDot => false,
+            Token::DotDotEqual => true,
+            _ => return Ok(Pattern::Literal(Expression::NumberLiteral(start))),
+        };
+        self.advance();
+
+        Ok(Pattern::Range {
+            start,
+            end: self.pattern_number()?,
+            inclusive,
+        })
+    }
+
+    fn pattern_number(&mut self) -> Result<f64, String> {
+        let negative = self.peek() == Token::Minus;
+        if negative {
+            self.advance();
+        }
+        match self.advance() {
+            Token::NumberLiteral(n) if negative => Ok(-n),
+            Token::NumberLiteral(n) => Ok(n),
+            _ => Err("Expected number in pattern".to_string()),
+        }
+    }
+
+    fn variant_pattern(
+        &mut self,
+        enum_name: Option<String>,
+        variant: String,
+    ) -> Result<Pattern, String> {
+        let mut fields = Vec::new();
+
+        if self.peek() == Token::LeftParen {
+            self.advance();
+            while self.peek() != Token::RightParen {
+                fields.push(self.pattern()?);
+                if self.peek() != Token::Comma {
+                    break;
+                }
+                self.advance();
+            }
+
+            if self.peek() != Token::RightParen {
+                return Err("Expected ')' after variant patterns".to_string());
+            }
+            self.advance();
+        }
+
+        Ok(Pattern::Variant {
+            enum_name,
+            variant,
+            fields,
+        })
+    }
+
+    fn list_pattern(&mut self) -> Result<Pattern, String> {
+        let mut elements = Vec::new();
+        let mut rest = None;
+
+        while self.peek() != Token::RightBracket {
+            if self.peek() == Token::DotDot {
+                self.advance();
+                rest = match self.peek() {
+                    Token::Identifier(name) => {
+                        self.advance();
+                        Some(Some(name))
+                    }
+                    _ => Some(None),
+                };
+                break;
+            }
+
+            elements.push(self.pattern()?);
+            if self.peek() != Token::Comma {
+                break;
+            }
+            self.advance();
+        }
+
+        if self.peek() != Token::RightBracket {
+            return Err("Expected ']' after list pattern".to_string());
+        }
+        self.advance();
+
+        Ok(Pattern::List { elements, rest })
+    }
+
+    fn struct_pattern(&mut self, name: String) -> Result<Pattern, String> {
+        self.advance();
+
+        let mut fields = Vec::new();
+        while self.peek() != Token::RightBrace {
+            let field = match self.advance() {
+                Token::Identifier(field) => field,
+                _ => return Err("Expected field name in struct pattern".to_string()),
+            };
+
+            let pattern = if self.peek() == Token::Colon {
+                self.advance();
+                self.pattern()?
+            } else {
+                Pattern::Binding(field.clone())
+            };
+            fields.push((field, pattern));
+
+            if self.peek() != Token::Comma {
+                break;
+            }
+            self.advance();
+        }
+
+        if self.peek() != Token::RightBrace {
+            return Err("Expected '}' after struct pattern".to_string());
+        }
+        self.advance();
+
+        Ok(Pattern::Struct { name, fields })
+    }
+
+    fn import_statement(&mut self) -> Result<Statement, String> {
+        self.advance();
+        let path = match self.advance() {
+            Token::StringLiteral(path) => path,
+            _ => return Err("Expected module path after import".to_string()),
+        };
+        let mut token = self.current - 1;
+
+        let mut alias = None;
+        if self.peek() == Token::As {
+            self.advance();
+            alias = match self.advance() {
+                Token::Identifier(alias) => Some(alias),
+                _ => return Err("Expected module name after 'as'".to_string()),
+            };
+            token = self.current - 1;
+        }
+
+        if self.advance() != Token::Semicolon {
+            return Err("Expected ';' after import".to_string());
+        }
+
+        let name = alias.clone().unwrap_or_else(|| module_name(&path));
+        let detail = format!("import \"{}\"", path);
+        let declaration = self.record(SymbolKind::Module, &name, token, detail);
+        self.declare(name, Binding::Mutable, declaration);
+
+        Ok(Statement::Import { path, alias })
+    }
+
+    fn selective_import_statement(&mut self) -> Result<Statement, String> {
+        self.advance();
+        let path = match self.advance() {
+            Token::StringLiteral(path) => path,
+            Token::Identifier(module) => {
+                self.reference(&module, self.current - 1);
+                module
+            }
+            _ => return Err("Expected module after from".to_string()),
+        };
+
+        if self.advance() != Token::Import {
+            return Err("Expected 'import' after module".to_string());
+        }
+        let start = self.current;
+        let names = self.name_list()?;
+
+        for (offset, name) in names.iter().enumerate() {
+            // Names alternate with commas.
+            let token = start + offset * 2;
+            let detail = format!("from {} import {}", path, name);
+            let declaration = self.record(SymbolKind::Variable, name, token, detail);
+            self.declare(name.clone(), Binding::Mutable, declaration);
+        }
+
+        Ok(Statement::FromImport { path, names })
+    }
 
-pub struct Parser {
-    tokens: Vec<Token>,
-    current: usize,
-}
+    fn export_statement(&mut self) -> Result<Statement, String> {
+        self.advance();
 
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, current: 0 }
+        match self.peek() {
+            Token::Var | Token::Let | Token::Const | Token::Struct | Token::Class | Token::Enum => {
+                Ok(Statement::Export(Box::new(self.statement_kind()?)))
+            }
+            Token::Func if matches!(self.peek_next(), Token::Identifier(_)) => {
+                Ok(Statement::Export(Box::new(self.statement_kind()?)))
+            }
+            Token::Identifier(_) => {
+                let start = self.current;
+                let names = self.name_list()?;
+                for (offset, name) in names.iter().enumerate() {
+                    self.reference(name, start + offset * 2);
+                }
+                Ok(Statement::ExportNames(names))
+            }
+            _ => Err("Expected declaration or names after export".to_string()),
+        }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Statement>, String> {
-        let mut statements = Vec::new();
+    fn name_list(&mut self) -> Result<Vec<String>, String> {
+        let mut names = Vec::new();
 
-        while !self.is_at_end() {
-            if let Ok(stmt) = self.statement() {
-                statements.push(stmt);
+        loop {
+            match self.advance() {
+                Token::Identifier(name) => names.push(name),
+                _ => return Err("Expected name".to_string()),
+            }
+            if self.peek() != Token::Comma {
+                break;
             }
+            self.advance();
         }
 
-        Ok(statements)
+        if self.advance() != Token::Semicolon {
+            return Err("Expected ';' after names".to_string());
+        }
+
+        Ok(names)
     }
 
-    fn statement(&mut self) -> Result<Statement, String> {
-        match self.peek() {
-            Token::Var => self.variable_declaration(),
-            Token::Identifier(_) => self.variable_assignment(),
-            Token::Print => self.print_statement(),
-            Token::If => self.if_statement(),
-            Token::While => self.while_statement(),
-            _ => Err("Unexpected token in statement".to_string()),
+    fn throw_statement(&mut self) -> Result<Statement, String> {
+        self.advance();
+        let value = self.expression()?;
+
+        if self.advance() != Token::Semicolon {
+            return Err("Expected ';' after throw value".to_string());
         }
+
+        Ok(Statement::Throw(value))
     }
 
-    fn variable_declaration(&mut self) -> Result<Statement, String> {
+    fn try_statement(&mut self) -> Result<Statement, String> {
         self.advance();
-        let name = match self.advance() {
-            Token::Identifier(name) => name,
-            _ => return Err("Expected variable name".to_string()),
-        };
+        let body = self.block_statements()?;
 
-        let mut value_type = None;
-        if let Token::Colon = self.peek() {
+        let mut catch = None;
+        if self.peek() == Token::Catch {
             self.advance();
-            value_type = Some(self.parse_type()?);
+            let mut name = None;
+            if let Token::Identifier(identifier) = self.peek() {
+                self.advance();
+                name = Some(identifier);
+            }
+            let local = name.clone().map(|name| {
+                (
+                    name.clone(),
+                    SymbolKind::Variable,
+                    format!("catch {}", name),
+                )
+            });
+            let body = self.scoped(local, Self::block_statements)?;
+            catch = Some(CatchClause { name, body });
         }
 
-        if let Token::Assign = self.advance() {
-            let value = self.expression()?;
-            if let Token::Semicolon = self.advance() {
-                return Ok(Statement::VariableDeclaration {
-                    name,
-                    value,
-                    value_type,
-                });
-            }
+        let mut finally = None;
+        if self.peek() == Token::Finally {
+            self.advance();
+            finally = Some(self.block_statements()?);
         }
-        Err("Invalid variable declaration".to_string())
+
+        if catch.is_none() && finally.is_none() {
+            return Err("Expected 'catch' or 'finally' after try block".to_string());
+        }
+
+        Ok(Statement::Try {
+            body,
+            catch,
+            finally,
+        })
     }
 
-    fn variable_assignment(&mut self) -> Result<Statement, String> {
+    fn thread_statement(&mut self) -> Result<Statement, String> {
+        self.advance();
+
+        let body = Box::new(self.block()?);
+
+        Ok(Statement::Thread(body))
+    }
+
+    fn test_statement(&mut self) -> Result<Statement, String> {
+        self.advance();
         let name = match self.advance() {
-            Token::Identifier(name) => name,
-            _ => return Err("Expected variable name".to_string()),
+            Token::StringLiteral(name) => name,
+            _ => return Err("Expected test name after test".to_string()),
         };
 
-        if matches!(
-            self.peek(),
-            Token::Increment | Token::Decrement | Token::Assign
-        ) {
-            let expr = match self.advance() {
-                Token::Increment => {
-                    Expression::Increment(Box::new(Expression::Identifier(name.clone())))
-                }
-                Token::Decrement => {
-                    Expression::Decrement(Box::new(Expression::Identifier(name.clone())))
-                }
-                Token::Assign => {
-                    let value = self.expression()?;
-                    Expression::Binary {
-                        left: Box::new(Expression::Identifier(name.clone())),
-                        operator: Operator::Assign,
-                        right: Box::new(value),
-                    }
-                }
-                _ => {
-                    return Err("Expected increment, decrement, or assignment operator".to_string())
-                }
-            };
+        let body = self.block_statements()?;
 
-            if let Token::Semicolon = self.advance() {
-                return Ok(Statement::VariableAssignment {
-                    name,
-                    value: expr,
-                    value_type: None,
-                });
-            }
-        }
-        Err("Invalid variable assignment".to_string())
+        Ok(Statement::Test { name, body })
     }
 
-    fn print_statement(&mut self) -> Result<Statement, String> {
+    fn select_statement(&mut self) -> Result<Statement, String> {
         self.advance();
-        if self.peek() == Token::LeftParen {
-            self.advance();
-            let value = self.expression()?;
-            if self.peek() == Token::RightParen {
+
+        if self.peek() != Token::LeftBrace {
+            return Err("Expected '{' after select".to_string());
+        }
+        self.advance();
+
+        let mut arms = Vec::new();
+        while !matches!(self.peek(), Token::RightBrace | Token::EOF) {
+            arms.push(self.select_arm()?);
+
+            if self.peek() == Token::Comma {
                 self.advance();
-                if self.peek() == Token::Semicolon {
-                    self.advance();
-                    return Ok(Statement::Print(value));
-                }
             }
         }
-        Err("Invalid print statement".to_string())
-    }
 
-    fn if_statement(&mut self) -> Result<Statement, String> {
+        if self.peek() != Token::RightBrace {
+            return Err("Expected '}' to end select".to_string());
+        }
         self.advance();
 
-        let condition = self.expression()?;
-        let then_branch = Box::new(self.block()?);
+        if arms.is_empty() {
+            return Err("Expected at least one arm in select".to_string());
+        }
+        let timeouts = arms
+            .iter()
+            .filter(|arm| matches!(arm, SelectArm::Timeout { .. }))
+            .count();
+        if timeouts > 1 {
+            return Err("Select can only have one timeout arm".to_string());
+        }
 
-        let else_branch = if self.peek() == Token::Else {
-            self.advance();
-            Some(Box::new(self.block()?))
-        } else {
-            None
+        Ok(Statement::Select(arms))
+    }
+
+    fn select_arm(&mut self) -> Result<SelectArm, String> {
+        let kind = match self.advance() {
+            Token::Identifier(kind) if kind == "recv" || kind == "timeout" => kind,
+            _ => return Err("Expected 'recv' or 'timeout' in select arm".to_string()),
         };
+        let value = self.expression()?;
 
-        Ok(Statement::If {
-            condition,
-            then_branch,
-            else_branch,
-        })
-    }
+        let mut binding = None;
+        if kind == "recv" && self.peek() == Token::As {
+            self.advance();
+            binding = match self.advance() {
+                Token::Identifier(name) => Some(name),
+                _ => return Err("Expected variable name after 'as'".to_string()),
+            };
+        }
 
-    fn while_statement(&mut self) -> Result<Statement, String> {
+        if self.peek() != Token::FatArrow {
+            return Err("Expected '=>' in select arm".to_string());
+        }
         self.advance();
 
-        let condition = self.expression()?;
-        let body = Box::new(self.block()?);
+        let local = binding.clone().map(|name| {
+            (
+                name.clone(),
+                SymbolKind::Variable,
+                format!("recv as {}", name),
+            )
+        });
+        let body = self.scoped(local, |parser| {
+            if parser.peek() == Token::LeftBrace {
+                parser.block()
+            } else {
+                parser.statement()
+            }
+        })?;
 
-        Ok(Statement::While { condition, body })
+        if kind == "recv" {
+            Ok(SelectArm::Receive {
+                channel: value,
+                binding,
+                body,
+            })
+        } else {
+            Ok(SelectArm::Timeout {
+                duration: value,
+                body,
+            })
+        }
     }
 
     fn block(&mut self) -> Result<Statement, String> {
+        Ok(Statement::Block(self.block_statements()?))
+    }
+
+    fn block_statements(&mut self) -> Result<Vec<Statement>, String> {
+        self.with_struct_literals(true, |parser| {
+            parser.scoped(Vec::new(), Self::block_contents)
+        })
+    }
+
+    fn block_contents(&mut self) -> Result<Vec<Statement>, String> {
         let mut statements = Vec::new();
 
         if self.peek() != Token::LeftBrace {
@@ -154,7 +1493,7 @@ impl Parser {
         }
         self.advance();
 
-        while self.peek() != Token::RightBrace {
+        while !matches!(self.peek(), Token::RightBrace | Token::EOF) {
             statements.push(self.statement()?);
         }
 
@@ -163,10 +1502,11 @@ impl Parser {
         }
         self.advance();
 
-        Ok(Statement::Block(statements))
+        Ok(statements)
     }
 
     fn inline_if(&mut self) -> Result<Expression, String> {
+        let branch = self.branch_point(2);
         self.advance();
         let condition = Box::new(self.expression()?);
 
@@ -204,36 +1544,64 @@ impl Parser {
             else_branch = Some(Box::new(self.expression()?));
         }
 
+        // The `then` and `else` arms, with the `elif`s between them.
+        self.branch_points[branch].arms += elif_branches.len();
         Ok(Expression::InlineIf {
             condition,
             then_branch,
             elif_branches,
             else_branch: else_branch.unwrap_or(Box::new(Expression::Nil)),
+            branch,
         })
     }
 
+    // Struct literals are not allowed directly in `if`/`while` conditions,
+    // where `name {` starts the body instead.
+    fn condition(&mut self) -> Result<Expression, String> {
+        self.with_struct_literals(false, Self::expression)
+    }
+
+    fn with_struct_literals<T>(
+        &mut self,
+        allowed: bool,
+        parse: impl FnOnce(&mut Self) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let previous = std::mem::replace(&mut self.struct_literals, allowed);
+        let result = parse(self);
+        self.struct_literals = previous;
+        result
+    }
+
     fn expression(&mut self) -> Result<Expression, String> {
         if self.peek() == Token::If {
             self.inline_if()
         } else {
-            self.comparison()
+            self.binary(LOWEST_PRECEDENCE)
         }
     }
 
-    fn comparison(&mut self) -> Result<Expression, String> {
-        let mut expr = self.term()?;
+    fn binary(&mut self, min_precedence: u8) -> Result<Expression, String> {
+        let mut expr = self.unary()?;
 
-        while matches!(
-            self.peek(),
-            Token::EqualEqual
-                | Token::NotEqual
-                | Token::LessThan
-                | Token::LessThanEqual
-                | Token::GreaterThan
-                | Token::GreaterThanEqual
-        ) {
+        while let Some((precedence, right_associative)) = infix_precedence(&self.peek()) {
+            if precedence < min_precedence {
+                break;
+            }
+            if self.peek() == Token::Is {
+                self.advance();
+                expr = Expression::Is {
+                    value: Box::new(expr),
+                    value_type: self.parse_type()?,
+                };
+                continue;
+            }
             let operator = self.parse_operator()?;
-            let right = self.term()?;
+            let next = if right_associative {
+                precedence
+            } else {
+                precedence + 1
+            };
+            let right = self.binary(next)?;
             expr = Expression::Binary {
                 left: Box::new(expr),
                 operator,
@@ -244,68 +1612,93 @@ impl Parser {
         Ok(expr)
     }
 
-    fn term(&mut self) -> Result<Expression, String> {
-        let mut expr = self.factor()?;
-
-        while matches!(self.peek(), Token::Plus | Token::Minus) {
+    fn unary(&mut self) -> Result<Expression, String> {
+        if matches!(self.peek(), Token::Not | Token::Minus) {
             let operator = self.parse_operator()?;
-            let right = self.factor()?;
-            expr = Expression::Binary {
-                left: Box::new(expr),
+            let right = self.binary(PREFIX_OPERAND)?;
+            return Ok(Expression::Unary {
                 operator,
                 right: Box::new(right),
-            };
+            });
         }
 
-        Ok(expr)
+        self.call()
     }
 
-    fn factor(&mut self) -> Result<Expression, String> {
-        let mut expr = self.logical()?;
+    fn call(&mut self) -> Result<Expression, String> {
+        let mut expr = self.primary()?;
 
-        while matches!(
-            self.peek(),
-            Token::Star | Token::Slash | Token::Percent | Token::Pow
-        ) {
-            let operator = self.parse_operator()?;
-            let right = self.logical()?;
-            expr = Expression::Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            };
+        loop {
+            match self.peek() {
+                Token::LeftParen => {
+                    self.advance();
+                    let arguments = self.arguments()?;
+                    expr = Expression::Call {
+                        callee: Box::new(expr),
+                        arguments,
+                    };
+                }
+                Token::LeftBracket => {
+                    self.advance();
+                    let index = self.expression()?;
+                    if self.peek() != Token::RightBracket {
+                        return Err("Expected ']' after index".to_string());
+                    }
+                    self.advance();
+                    expr = Expression::Index {
+                        object: Box::new(expr),
+                        index: Box::new(index),
+                    };
+                }
+                Token::Dot => {
+                    self.advance();
+                    let name = match self.advance() {
+                        Token::Identifier(name) => name,
+                        _ => return Err("Expected property name after '.'".to_string()),
+                    };
+                    expr = Expression::Get {
+                        object: Box::new(expr),
+                        name,
+                    };
+                }
+                Token::Question => {
+                    self.advance();
+                    if self.function_depth == 0 {
+                        return Err("'?' can only be used inside a function".to_string());
+                    }
+                    expr = Expression::Propagate(Box::new(expr));
+                }
+                _ => break,
+            }
         }
 
         Ok(expr)
     }
 
-    fn logical(&mut self) -> Result<Expression, String> {
-        let mut expr = self.unary()?;
+    fn arguments(&mut self) -> Result<Vec<Expression>, String> {
+        let arguments =
+            self.with_struct_literals(true, |parser| parser.expression_list(Token::RightParen))?;
 
-        while matches!(self.peek(), Token::And | Token::Or) {
-            let operator = self.parse_operator()?;
-            let right = self.unary()?;
-            expr = Expression::Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            };
+        if self.peek() != Token::RightParen {
+            return Err("Expected ')' after arguments".to_string());
         }
+        self.advance();
 
-        Ok(expr)
+        Ok(arguments)
     }
 
-    fn unary(&mut self) -> Result<Expression, String> {
-        if matches!(self.peek(), Token::Not) {
-            let operator = self.parse_operator()?;
-            let right = self.unary()?;
-            return Ok(Expression::Unary {
-                operator,
-                right: Box::new(right),
-            });
+    fn expression_list(&mut self, closing: Token) -> Result<Vec<Expression>, String> {
+        let mut expressions = Vec::new();
+
+        while self.peek() != closing {
+            expressions.push(self.expression()?);
+            if self.peek() != Token::Comma {
+                break;
+            }
+            self.advance();
         }
 
-        self.primary()
+        Ok(expressions)
     }
 
     fn primary(&mut self) -> Result<Expression, String> {
@@ -322,13 +1715,21 @@ impl Parser {
                 self.advance();
                 Ok(Expression::BooleanLiteral(b))
             }
+            Token::Nil => {
+                self.advance();
+                Ok(Expression::Nil)
+            }
             Token::Identifier(id) => {
                 self.advance();
+                self.reference(&id, self.current - 1);
+                if self.struct_literals && self.at_struct_literal() {
+                    return self.struct_literal(id);
+                }
                 Ok(Expression::Identifier(id))
             }
             Token::LeftParen => {
                 self.advance();
-                let expr = self.expression()?;
+                let expr = self.with_struct_literals(true, Self::expression)?;
                 if self.peek() == Token::RightParen {
                     self.advance();
                     Ok(expr)
@@ -336,10 +1737,90 @@ impl Parser {
                     Err("Expected ')' after expression".to_string())
                 }
             }
+            Token::LeftBracket => {
+                self.advance();
+                let elements = self.with_struct_literals(true, |parser| {
+                    parser.expression_list(Token::RightBracket)
+                })?;
+                if self.peek() == Token::RightBracket {
+                    self.advance();
+                    Ok(Expression::List(elements))
+                } else {
+                    Err("Expected ']' after list elements".to_string())
+                }
+            }
+            Token::Func => {
+                self.advance();
+                Ok(Expression::Function(self.function(None)?))
+            }
+            Token::Match => self.match_expression(),
+            Token::This => {
+                self.advance();
+                if self.classes.is_empty() {
+                    return Err("Cannot use 'this' outside of a class".to_string());
+                }
+                Ok(Expression::This)
+            }
+            Token::Super => {
+                self.advance();
+                match self.classes.last() {
+                    None => return Err("Cannot use 'super' outside of a class".to_string()),
+                    Some(false) => {
+                        return Err("Cannot use 'super' in a class without superclass".to_string())
+                    }
+                    Some(true) => {}
+                }
+                if self.advance() != Token::Dot {
+                    return Err("Expected '.' after 'super'".to_string());
+                }
+                match self.advance() {
+                    Token::Identifier(method) => Ok(Expression::Super(method)),
+                    _ => Err("Expected superclass method name".to_string()),
+                }
+            }
+            Token::Pipe | Token::Or => self.lambda(),
             _ => Err("Unexpected token in expression".to_string()),
         }
     }
 
+    fn at_struct_literal(&self) -> bool {
+        self.peek() == Token::LeftBrace
+            && match self.peek_at(1) {
+                Token::RightBrace => true,
+                Token::Identifier(_) => self.peek_at(2) == Token::Colon,
+                _ => false,
+            }
+    }
+
+    fn struct_literal(&mut self, name: String) -> Result<Expression, String> {
+        self.advance();
+
+        let mut fields = Vec::new();
+        while self.peek() != Token::RightBrace {
+            let field = match self.advance() {
+                Token::Identifier(field) => field,
+                _ => return Err("Expected field name in struct literal".to_string()),
+            };
+            if self.peek() != Token::Colon {
+                return Err("Expected ':' after field name".to_string());
+            }
+            self.advance();
+            fields.push((field, self.expression()?));
+
+            if self.peek() != Token::Comma {
+                break;
+            }
+            self.advance();
+        }
+
+        if self.peek() != Token::RightBrace {
+            return Err("Expected '}' to end struct literal".to_string());
+        }
+        self.advance();
+
+        Ok(Expression::StructLiteral { name, fields })
+    }
+
     fn parse_operator(&mut self) -> Result<Operator, String> {
         match self.advance() {
             Token::Plus => Ok(Operator::Plus),
@@ -351,6 +1832,7 @@ impl Parser {
 
             Token::And => Ok(Operator::And),
             Token::Or => Ok(Operator::Or),
+            Token::Coalesce => Ok(Operator::Coalesce),
             Token::Not => Ok(Operator::Not),
 
             Token::EqualEqual => Ok(Operator::EqualEqual),
@@ -359,6 +1841,7 @@ impl Parser {
             Token::LessThanEqual => Ok(Operator::LessThanEqual),
             Token::GreaterThan => Ok(Operator::GreaterThan),
             Token::GreaterThanEqual => Ok(Operator::GreaterThanEqual),
+            Token::InstanceOf => Ok(Operator::InstanceOf),
 
             _ => Err("Unexpected token in operator".to_string()),
         }
@@ -369,16 +1852,31 @@ impl Parser {
             Token::StringType => Ok(Type::String),
             Token::IntType => Ok(Type::Int),
             Token::BooleanType => Ok(Type::Boolean),
+            Token::Identifier(name) => {
+                self.reference(&name, self.current - 1);
+                Ok(Type::Named(name))
+            }
             _ => Err("Unexpected token in type".to_string()),
         }
     }
 
     fn peek(&self) -> Token {
-        self.tokens[self.current].clone()
+        self.tokens.get(self.current).cloned().unwrap_or(Token::EOF)
+    }
+
+    fn peek_next(&self) -> Token {
+        self.peek_at(1)
+    }
+
+    fn peek_at(&self, offset: usize) -> Token {
+        self.tokens
+            .get(self.current + offset)
+            .cloned()
+            .unwrap_or(Token::EOF)
     }
 
     fn advance(&mut self) -> Token {
-        let token = self.tokens[self.current].clone();
+        let token = self.peek();
         self.current += 1;
         token
     }
@@ -388,9 +1886,76 @@ impl Parser {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum Type {
     String,
     Int,
     Boolean,
+    Named(String),
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::String => write!(f, "String"),
+            Type::Int => write!(f, "Int"),
+            Type::Boolean => write!(f, "Bool"),
+            Type::Named(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+fn parameter_locals(parameters: &[Parameter]) -> Vec<Local> {
+    parameters
+        .iter()
+        .map(|parameter| {
+            let detail = parameter_list(std::slice::from_ref(parameter));
+            (parameter.name.clone(), SymbolKind::Parameter, detail)
+        })
+        .collect()
+}
+
+fn parameter_list(parameters: &[Parameter]) -> String {
+    parameters
+        .iter()
+        .map(|parameter| match &parameter.value_type {
+            Some(value_type) => format!("{}: {}", parameter.name, value_type),
+            None => parameter.name.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn signature(function: &FunctionDeclaration) -> String {
+    let name = function.name.as_deref().unwrap_or_default();
+    let parameters = parameter_list(&function.parameters);
+    match &function.return_type {
+        Some(return_type) => format!("func {}({}): {}", name, parameters, return_type),
+        None => format!("func {}({})", name, parameters),
+    }
+}
+
+pub fn pattern_bindings(pattern: &Pattern, names: &mut Vec<String>) {
+    match pattern {
+        Pattern::Binding(name) => names.push(name.clone()),
+        Pattern::Variant { fields, .. } => {
+            fields
+                .iter()
+                .for_each(|field| pattern_bindings(field, names));
+        }
+        Pattern::List { elements, rest } => {
+            elements
+                .iter()
+                .for_each(|element| pattern_bindings(element, names));
+            if let Some(Some(name)) = rest {
+                names.push(name.clone());
+            }
+        }
+        Pattern::Struct { fields, .. } => {
+            fields
+                .iter()
+                .for_each(|(_, field)| pattern_bindings(field, names));
+        }
+        Pattern::Wildcard | Pattern::Literal(_) | Pattern::Range { .. } => {}
+    }
 }
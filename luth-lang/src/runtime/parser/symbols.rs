@@ -0,0 +1,43 @@
+use std::ops::Range;
+
+/// Where names are declared and used, recorded while parsing. Positions
+/// are token indices into the parser's input; editor tooling maps them
+/// back to source locations.
+#[derive(Debug, Default)]
+pub struct Symbols {
+    pub declarations: Vec<Declaration>,
+    pub references: Vec<Reference>,
+    // The tokens each scope covers. Scope 0 is the whole program.
+    pub scopes: Vec<Range<usize>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub token: usize,
+    pub scope: usize,
+    // A one-line description such as `let count: Int`.
+    pub detail: String,
+    // The struct or class a method belongs to.
+    pub container: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Reference {
+    pub token: usize,
+    pub declaration: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Variable,
+    Constant,
+    Parameter,
+    Function,
+    Method,
+    Struct,
+    Class,
+    Enum,
+    Module,
+}
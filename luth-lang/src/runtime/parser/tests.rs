@@ -0,0 +1,201 @@
+use crate::runtime::lexer::lexer::{lexer, lexer_with_lines};
+use crate::runtime::parser::ast::{Expression, Operator, Statement};
+use crate::runtime::parser::parser::Parser;
+
+// Parses a single expression statement and prints it fully parenthesized.
+fn grouping(source: &str) -> String {
+    let mut parser = Parser::new(lexer(&format!("{};", source)));
+    match parser.parse().unwrap().as_slice() {
+        [Statement::Located { statement, .. }] => match statement.as_ref() {
+            Statement::Expression(expr) => render(expr),
+            statement => panic!("expected an expression, found {:?}", statement),
+        },
+        statements => panic!("expected one expression, found {:?}", statements),
+    }
+}
+
+fn render(expr: &Expression) -> String {
+    match expr {
+        Expression::NumberLiteral(n) => n.to_string(),
+        Expression::BooleanLiteral(b) => b.to_string(),
+        Expression::Identifier(name) => name.clone(),
+        Expression::Binary {
+            left,
+            operator,
+            right,
+        } => format!("({} {} {})", render(left), symbol(operator), render(right)),
+        Expression::Unary { operator, right } => format!("({}{})", symbol(operator), render(right)),
+        Expression::Call { callee, arguments } => {
+            let arguments: Vec<String> = arguments.iter().map(render).collect();
+            format!("{}({})", render(callee), arguments.join(", "))
+        }
+        Expression::Is { value, value_type } => {
+            format!("({} is {:?})", render(value), value_type)
+        }
+        expr => panic!("cannot render {:?}", expr),
+    }
+}
+
+fn symbol(operator: &Operator) -> &'static str {
+    match operator {
+        Operator::Plus => "+",
+        Operator::Minus => "-",
+        Operator::Star => "*",
+        Operator::Slash => "/",
+        Operator::Percent => "%",
+        Operator::Pow => "**",
+        Operator::And => "and",
+        Operator::Or => "or",
+        Operator::Not => "!",
+        Operator::Coalesce => "??",
+        Operator::EqualEqual => "==",
+        Operator::NotEqual => "!=",
+        Operator::LessThan => "<",
+        Operator::LessThanEqual => "<=",
+        Operator::GreaterThan => ">",
+        Operator::GreaterThanEqual => ">=",
+        Operator::InstanceOf => "instanceof",
+    }
+}
+
+#[test]
+fn multiplication_binds_tighter_than_addition() {
+    assert_eq!(grouping("1 + 2 * 3"), "(1 + (2 * 3))");
+    assert_eq!(grouping("1 * 2 + 3"), "((1 * 2) + 3)");
+    assert_eq!(grouping("a % b - c / d"), "((a % b) - (c / d))");
+}
+
+#[test]
+fn arithmetic_is_left_associative() {
+    assert_eq!(grouping("1 - 2 - 3"), "((1 - 2) - 3)");
+    assert_eq!(grouping("8 / 4 / 2"), "((8 / 4) / 2)");
+}
+
+#[test]
+fn power_is_right_associative() {
+    assert_eq!(grouping("2 ** 3 ** 2"), "(2 ** (3 ** 2))");
+}
+
+#[test]
+fn power_binds_tighter_than_multiplication() {
+    assert_eq!(grouping("2 * 3 ** 2"), "(2 * (3 ** 2))");
+    assert_eq!(grouping("3 ** 2 * 2"), "((3 ** 2) * 2)");
+}
+
+#[test]
+fn power_binds_tighter_than_prefix_operators() {
+    assert_eq!(grouping("-2 ** 2"), "(-(2 ** 2))");
+    assert_eq!(grouping("2 ** -1"), "(2 ** (-1))");
+    assert_eq!(grouping("!a ** b"), "(!(a ** b))");
+}
+
+#[test]
+fn prefix_operators_bind_tighter_than_multiplication() {
+    assert_eq!(grouping("-a * b"), "((-a) * b)");
+    assert_eq!(grouping("a - -b"), "(a - (-b))");
+    assert_eq!(grouping("!a == b"), "((!a) == b)");
+    assert_eq!(grouping("not a and b"), "((!a) and b)");
+}
+
+#[test]
+fn comparisons_bind_tighter_than_logical_operators() {
+    assert_eq!(grouping("a == b && c"), "((a == b) and c)");
+    assert_eq!(grouping("a < b or c >= d"), "((a < b) or (c >= d))");
+    assert_eq!(grouping("a != b and c"), "((a != b) and c)");
+}
+
+#[test]
+fn relational_binds_tighter_than_equality() {
+    assert_eq!(grouping("a < b == c > d"), "((a < b) == (c > d))");
+    assert_eq!(
+        grouping("a instanceof B == true"),
+        "((a instanceof B) == true)"
+    );
+}
+
+#[test]
+fn type_tests_bind_like_comparisons() {
+    assert_eq!(grouping("a + 1 is Int"), "((a + 1) is Int)");
+    assert_eq!(grouping("a is String or b"), "((a is String) or b)");
+    assert_eq!(grouping("not a is Bool"), "((!a) is Boolean)");
+}
+
+#[test]
+fn arithmetic_binds_tighter_than_comparison() {
+    assert_eq!(grouping("a + 1 < b * 2"), "((a + 1) < (b * 2))");
+}
+
+#[test]
+fn and_binds_tighter_than_or() {
+    assert_eq!(grouping("a or b and c"), "(a or (b and c))");
+    assert_eq!(grouping("a and b or c"), "((a and b) or c)");
+    assert_eq!(grouping("a || b && c || d"), "((a or (b and c)) or d)");
+}
+
+#[test]
+fn coalesce_binds_loosest() {
+    assert_eq!(grouping("a ?? b or c"), "(a ?? (b or c))");
+    assert_eq!(grouping("a ?? b ?? c"), "(a ?? (b ?? c))");
+    assert_eq!(grouping("a == b ?? c"), "((a == b) ?? c)");
+}
+
+#[test]
+fn logical_operators_are_left_associative() {
+    assert_eq!(grouping("a and b and c"), "((a and b) and c)");
+    assert_eq!(grouping("a or b or c"), "((a or b) or c)");
+}
+
+#[test]
+fn parentheses_override_precedence() {
+    assert_eq!(grouping("(1 + 2) * 3"), "((1 + 2) * 3)");
+    assert_eq!(grouping("(-2) ** 2"), "((-2) ** 2)");
+    assert_eq!(grouping("a == (b && c)"), "(a == (b and c))");
+}
+
+#[test]
+fn calls_bind_tighter_than_every_operator() {
+    assert_eq!(grouping("-f(x) ** 2"), "(-(f(x) ** 2))");
+    assert_eq!(grouping("f(a + b) * c"), "(f((a + b)) * c)");
+}
+
+#[test]
+fn minus_is_not_part_of_number_literals() {
+    assert_eq!(grouping("x-1"), "(x - 1)");
+    assert_eq!(grouping("x - -1"), "(x - (-1))");
+}
+
+#[test]
+fn statements_serialize_to_stable_json() {
+    let (tokens, lines) = lexer_with_lines("print(-x);");
+    let statements = Parser::with_lines(tokens, lines).parse().unwrap();
+    assert_eq!(
+        serde_json::to_string(&statements).unwrap(),
+        r#"[{"Located":{"line":1,"statement":{"Print":{"Unary":{"operator":"Minus","right":{"Identifier":"x"}}}}}}]"#
+    );
+}
+
+#[test]
+fn constants_cannot_be_redeclared_in_the_same_scope() {
+    let parse = |source: &str| Parser::new(lexer(source)).parse();
+    assert_eq!(
+        parse("const A = 1; var A = 5;").unwrap_err(),
+        "Cannot redeclare constant 'A'"
+    );
+    assert_eq!(
+        parse("let a = 1; let a = 2;").unwrap_err(),
+        "Cannot redeclare immutable variable 'a'"
+    );
+    assert!(parse("const A = 1; func f() { var A = 2; }").is_ok());
+    assert!(parse("var a = 1; var a = 2;").is_ok());
+}
+
+#[test]
+fn lambdas_with_expression_bodies_can_propagate_errors() {
+    let parse = |source: &str| Parser::new(lexer(source)).parse();
+    assert!(parse("var f = |r| r?;").is_ok());
+    assert!(parse("var f = |r| { return r?; };").is_ok());
+    assert_eq!(
+        parse("var r = nil; var x = r?;").unwrap_err(),
+        "'?' can only be used inside a function"
+    );
+}
@@ -0,0 +1,3 @@
+pub mod runner;
+#[cfg(test)]
+mod tests;
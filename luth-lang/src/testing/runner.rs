@@ -0,0 +1,91 @@
+use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex};
+
+use crate::runtime::interpreter::coverage::Coverage;
+use crate::runtime::interpreter::debugger::Event;
+use crate::runtime::interpreter::interpreter::Interpreter;
+use crate::runtime::lexer::lexer::lexer_with_lines;
+use crate::runtime::parser::ast::Statement;
+use crate::runtime::parser::parser::Parser;
+
+/// Files ending in this are searched for tests.
+pub const TEST_SUFFIX: &str = "_test.lt";
+
+#[derive(Debug)]
+pub struct Outcome {
+    pub name: String,
+    // Why the test failed, if it did.
+    pub failure: Option<String>,
+    // What the test printed, shown when it fails.
+    pub output: String,
+}
+
+/// Runs the tests in `source` whose names contain `filter`. Each one gets
+/// a fresh interpreter that runs the file's top level before the test, so
+/// no test sees what another left behind. With `coverage`, the file and
+/// the modules it imports are recorded into it.
+pub fn run_tests(
+    source: &str,
+    path: &Path,
+    filter: Option<&str>,
+    coverage: Option<&Arc<Mutex<Coverage>>>,
+) -> Result<Vec<Outcome>, String> {
+    let (tokens, lines) = lexer_with_lines(source);
+    let mut parser = Parser::with_lines(tokens, lines);
+    let statements = parser.parse()?;
+
+    let tests: Vec<_> = statements
+        .iter()
+        .filter_map(test)
+        .filter(|(name, _)| filter.is_none_or(|filter| name.contains(filter)))
+        .collect();
+    if let (Some(coverage), false) = (coverage, tests.is_empty()) {
+        // Keyed the way the interpreter sees the file.
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        coverage.lock().unwrap().register(&path, &parser);
+    }
+
+    let outcomes = tests
+        .into_iter()
+        .map(|(name, body)| run_test(&statements, path, name, body, coverage))
+        .collect();
+    Ok(outcomes)
+}
+
+fn test(statement: &Statement) -> Option<(String, Vec<Statement>)> {
+    match statement {
+        Statement::Test { name, body } => Some((name.clone(), body.clone())),
+        Statement::Located { statement, .. } => test(statement),
+        _ => None,
+    }
+}
+
+fn run_test(
+    statements: &[Statement],
+    path: &Path,
+    name: String,
+    body: Vec<Statement>,
+    coverage: Option<&Arc<Mutex<Coverage>>>,
+) -> Outcome {
+    let (output, printed) = mpsc::channel();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_script_path(path);
+    interpreter.set_output(output);
+    if let Some(coverage) = coverage {
+        interpreter.set_coverage(Arc::clone(coverage));
+    }
+    let result = interpreter.run_test(statements.to_vec(), body);
+
+    let output = printed
+        .try_iter()
+        .filter_map(|event| match event {
+            Event::Output { text, .. } => Some(text),
+            _ => None,
+        })
+        .collect();
+    Outcome {
+        name,
+        failure: result.err().map(|exception| exception.to_string()),
+        output,
+    }
+}
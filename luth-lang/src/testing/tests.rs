@@ -0,0 +1,131 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::runtime::interpreter::coverage::Coverage;
+
+use crate::testing::runner::{run_tests, Outcome};
+
+fn run(source: &str, filter: Option<&str>) -> Vec<Outcome> {
+    run_tests(source, Path::new("example_test.lt"), filter, None).unwrap()
+}
+
+#[test]
+fn runs_each_test_in_a_fresh_interpreter() {
+    let outcomes = run(
+        r#"
+var count = 0;
+func bump() { count = count + 1; return count; }
+
+test "first" { assert_eq(bump(), 1); }
+test "second" { assert_eq(bump(), 1); }
+"#,
+        None,
+    );
+
+    let names: Vec<_> = outcomes
+        .iter()
+        .map(|outcome| outcome.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["first", "second"]);
+    assert!(outcomes.iter().all(|outcome| outcome.failure.is_none()));
+}
+
+#[test]
+fn reports_failures_with_output() {
+    let outcomes = run(
+        r#"
+test "fails" {
+    print("checking");
+    assert(1 > 2, "one is not bigger");
+}
+test "also fails" { throw "boom"; }
+"#,
+        None,
+    );
+
+    assert_eq!(
+        outcomes[0].failure.as_deref(),
+        Some("Assertion failed: one is not bigger")
+    );
+    assert_eq!(outcomes[0].output, "checking\n");
+    assert_eq!(outcomes[1].failure.as_deref(), Some("boom"));
+}
+
+#[test]
+fn assert_eq_shows_where_values_differ() {
+    let outcomes = run(
+        r#"
+struct Point { x: Int, y: Int }
+test "lists" { assert_eq([1, [2, 3]], [1, [2, 4]]); }
+test "structs" { assert_eq(Point { x: 1, y: 2 }, Point { x: 1, y: 5 }, "moved"); }
+test "strings" { assert_eq("hello world", "hello there"); }
+test "not equal" { assert_ne(2, 1 + 1); }
+"#,
+        None,
+    );
+
+    let failures: Vec<_> = outcomes
+        .iter()
+        .map(|outcome| outcome.failure.clone().unwrap())
+        .collect();
+    assert_eq!(
+        failures[0],
+        "Assertion 'assert_eq' failed\n  left:  [1, [2, 3]]\n  right: [1, [2, 4]]\n  at [1][1]: 3 != 4"
+    );
+    assert_eq!(
+        failures[1],
+        "Assertion 'assert_eq' failed: moved\n  left:  Point { x: 1, y: 2 }\n  right: Point { x: 1, y: 5 }\n  at .y: 2 != 5"
+    );
+    assert!(failures[2].ends_with("  at character 6: 'w' != 't'"));
+    assert_eq!(
+        failures[3],
+        "Assertion 'assert_ne' failed\n  left:  2\n  right: 2"
+    );
+}
+
+#[test]
+fn filters_tests_by_name() {
+    let source = r#"
+test "parse numbers" { assert(true); }
+test "parse strings" { assert(true); }
+test "format" { assert(true); }
+"#;
+    assert_eq!(run(source, Some("parse")).len(), 2);
+    assert_eq!(run(source, Some("nothing")).len(), 0);
+}
+
+#[test]
+fn tests_are_skipped_when_running_a_program() {
+    let source = "test \"never\" { throw \"ran\"; }";
+    let statements =
+        crate::runtime::parser::parser::Parser::new(crate::runtime::lexer::lexer::lexer(source))
+            .parse()
+            .unwrap();
+    let mut interpreter = crate::runtime::interpreter::interpreter::Interpreter::new();
+    assert!(interpreter.run_test(statements, Vec::new()).is_ok());
+}
+
+#[test]
+fn records_lines_and_branches_covered() {
+    let source = r#"func sign(n) {
+    if n < 0 {
+        return "negative";
+    }
+    return if n == 0: "zero" else: "positive";
+}
+
+test "sign" {
+    assert_eq(sign(5), "positive");
+}
+"#;
+    let coverage = Arc::new(Mutex::new(Coverage::default()));
+    run_tests(source, Path::new("sign_test.lt"), None, Some(&coverage)).unwrap();
+
+    let lcov = coverage.lock().unwrap().lcov();
+    assert!(lcov.starts_with("TN:\nSF:sign_test.lt\n"));
+    assert!(lcov.contains("BRDA:2,0,0,0\nBRDA:2,0,1,1\n"));
+    assert!(lcov.contains("BRDA:5,1,0,0\nBRDA:5,1,1,1\nBRF:4\nBRH:2\n"));
+    assert!(lcov.contains("DA:3,0\n"));
+    assert!(lcov.contains("DA:9,1\n"));
+    assert!(lcov.ends_with("LF:6\nLH:5\nend_of_record\n"));
+}
@@ -0,0 +1,172 @@
+//! Runs every `.lt` fixture through `luth run` and compares what it prints
+//! with the `.out` and `.err` files next to its expectations, and its exit
+//! status with the `.status` file. A missing file expects no output at all,
+//! or a status of 0. A fixture whose threads print in no
+//! fixed order can say so with a `# golden: unordered` line, and its lines
+//! are then compared sorted.
+//!
+//! Set `LUTH_BLESS=1` to rewrite the expectations from the current output:
+//!
+//!     LUTH_BLESS=1 cargo test --test golden
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Each directory of fixtures and where their expectations are kept.
+const SUITES: &[(&str, &str)] = &[
+    ("tests/golden", "tests/golden"),
+    ("../docs/examples", "tests/golden/examples"),
+];
+
+#[test]
+fn golden() {
+    let bless = env::var_os("LUTH_BLESS").is_some_and(|value| value != "0");
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+    let mut failures = Vec::new();
+    let mut count = 0;
+    for (fixtures, expectations) in SUITES {
+        let fixtures = root.join(fixtures);
+        for fixture in fixtures_in(&fixtures) {
+            let relative = fixture.strip_prefix(&fixtures).unwrap();
+            let expected = root.join(expectations).join(relative);
+            count += 1;
+            if let Err(failure) = check(&fixtures, relative, &expected, bless) {
+                failures.push(format!("{}\n{}", fixture.display(), failure));
+            }
+        }
+    }
+
+    assert!(count > 0, "no fixtures found");
+    assert!(
+        failures.is_empty(),
+        "{} of {} fixtures failed (rerun with LUTH_BLESS=1 to accept):\n\n{}",
+        failures.len(),
+        count,
+        failures.join("\n\n")
+    );
+}
+
+fn fixtures_in(directory: &Path) -> Vec<PathBuf> {
+    let mut fixtures = Vec::new();
+    let mut entries: Vec<PathBuf> = fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            fixtures.extend(fixtures_in(&entry));
+        } else if entry.extension().is_some_and(|extension| extension == "lt") {
+            fixtures.push(entry);
+        }
+    }
+    fixtures
+}
+
+// Runs one fixture from its own directory, so imports and paths in errors
+// do not depend on where the tests run.
+fn check(fixtures: &Path, relative: &Path, expected: &Path, bless: bool) -> Result<(), String> {
+    let directory = fixtures.join(relative).parent().unwrap().to_path_buf();
+    let output = Command::new(env!("CARGO_BIN_EXE_luth"))
+        .arg("run")
+        .arg(relative.file_name().unwrap())
+        .current_dir(&directory)
+        .output()
+        .map_err(|error| format!("cannot run luth: {}", error))?;
+
+    let source = fs::read_to_string(fixtures.join(relative)).unwrap();
+    let unordered = source
+        .lines()
+        .any(|line| line.trim() == "# golden: unordered");
+    let directory = directory.canonicalize().unwrap();
+    let normalize = |bytes: &[u8]| {
+        let text = String::from_utf8_lossy(bytes).replace(&*directory.to_string_lossy(), "$DIR");
+        if unordered {
+            let mut lines: Vec<&str> = text.lines().collect();
+            lines.sort();
+            lines.iter().map(|line| format!("{}\n", line)).collect()
+        } else {
+            text
+        }
+    };
+    // Like empty output, a status of 0 has no file.
+    let status = match output.status.code() {
+        Some(0) => String::new(),
+        Some(code) => format!("{}\n", code),
+        None => "killed by a signal\n".to_string(),
+    };
+    let actual = [
+        ("out", normalize(&output.stdout)),
+        ("err", normalize(&output.stderr)),
+        ("status", status),
+    ];
+
+    let mut problems = Vec::new();
+    for (extension, actual) in actual {
+        let path = expected.with_extension(extension);
+        if bless {
+            bless_file(&path, &actual);
+            continue;
+        }
+        let expected = fs::read_to_string(&path).unwrap_or_default();
+        if expected != actual {
+            problems.push(format!("{}:\n{}", extension, diff(&expected, &actual)));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems.join("\n"))
+    }
+}
+
+fn bless_file(path: &Path, contents: &str) {
+    if contents.is_empty() {
+        let _ = fs::remove_file(path);
+    } else {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+}
+
+// A line diff of the expected and actual output: `-` lines are missing
+// and `+` lines are unexpected.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+
+    // common[i][j] is the longest common subsequence of the lines from
+    // expected[i..] and actual[j..].
+    let mut common = vec![vec![0; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = if expected[i] == actual[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            lines.push(format!("    {}", expected[i]));
+            i += 1;
+            j += 1;
+        } else if j < actual.len() && (i == expected.len() || common[i][j + 1] >= common[i + 1][j])
+        {
+            lines.push(format!("  + {}", actual[j]));
+            j += 1;
+        } else {
+            lines.push(format!("  - {}", expected[i]));
+            i += 1;
+        }
+    }
+    lines.join("\n")
+}